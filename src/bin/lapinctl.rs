@@ -0,0 +1,48 @@
+//! Sends a command to Le Petit Lapin. The arguments are the command, e.g.
+//! `lapinctl goto_workspace 2`. See the `ipc` module for the available
+//! commands. With `lapinctl subscribe`, events are printed as they happen,
+//! one per line.
+
+use le_petit_lapin::ipc;
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::net::Shutdown;
+use std::os::unix::net::UnixStream;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "-h" || args[0] == "--help" {
+        eprintln!("usage: lapinctl <command> [argument]");
+        process::exit(if args.is_empty() { 1 } else { 0 });
+    }
+
+    let path = ipc::socket_path();
+    let mut stream = UnixStream::connect(&path).unwrap_or_else(|e| {
+        eprintln!("lapinctl: cannot connect to {}: {e}", path.display());
+        process::exit(1);
+    });
+    let sent = writeln!(stream, "{}", args.join(" "));
+    if let Err(e) = sent.and_then(|_| stream.shutdown(Shutdown::Write)) {
+        eprintln!("lapinctl: cannot send the command: {e}");
+        process::exit(1);
+    }
+
+    // print as it arrives, as subscriptions never end.
+    let mut failed = false;
+    for line in BufReader::new(stream).lines() {
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("lapinctl: cannot read the reply: {e}");
+            process::exit(1);
+        });
+        if let Some(error) = line.strip_prefix("error: ") {
+            eprintln!("lapinctl: {error}");
+            failed = true;
+        } else if line != "ok" {
+            println!("{line}");
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
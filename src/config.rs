@@ -2,6 +2,103 @@
 
 use crate::layouts::*;
 use crate::rules::*;
+use crate::Lapin;
+use std::time;
+use xcb::x;
+
+/// A closure called when a window is managed, after it's placed. Returns
+/// `false` to veto it, leaving the window unmanaged.
+pub type ManageHook = Box<dyn FnMut(&mut Lapin, x::Window) -> bool>;
+
+/// A physical screen, to have its own settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Monitor {
+    /// The index of the screen, as detected by xinerama.
+    Index(usize),
+    /// The name of the RandR output showing the screen, like `"HDMI-1"`
+    /// (see `xrandr`).
+    Output(&'static str),
+}
+
+/// Settings of a single screen, overriding the ones of `Config`.
+pub struct ScreenConfig {
+    /// The screen these settings are for.
+    pub monitor: Monitor,
+    /// Outer gaps of the screen, replacing `Config::reserved_space`.
+    /// Defaults to `None`, to use the global one.
+    pub reserved_space: Option<(u16, u16, u16, u16)>,
+    /// Name of the layout the workspaces of the screen start with. Defaults
+    /// to `None`, to use the first one.
+    pub layout: Option<&'static str>,
+    /// Index of the workspace the screen starts on. Defaults to `None`, to
+    /// start on the first one.
+    pub workspace: Option<usize>,
+}
+
+impl ScreenConfig {
+    /// Creates settings for a screen without overriding anything.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use le_petit_lapin::*;
+    /// use le_petit_lapin::config::*;
+    /// let mut lapin = Lapin::connect();
+    /// // the bar only lives in the laptop screen.
+    /// let mut laptop = ScreenConfig::new(Monitor::Output("eDP-1"));
+    /// laptop.reserved_space = Some((20, 0, 0, 0));
+    /// let mut external = ScreenConfig::new(Monitor::Output("HDMI-1"));
+    /// external.layout = Some("Maximized");
+    /// external.workspace = Some(1);
+    /// lapin.config.screens = vec![laptop, external];
+    /// ```
+    pub fn new(monitor: Monitor) -> Self {
+        ScreenConfig {
+            monitor,
+            reserved_space: None,
+            layout: None,
+            workspace: None,
+        }
+    }
+}
+
+/// Layouts of a single workspace, overriding `Config::layouts`. The
+/// workspace is the same in every screen.
+pub struct WorkspaceConfig {
+    /// Index of the workspace in `Config::workspaces`.
+    pub workspace: usize,
+    /// Names of the layouts of `Config::layouts` available in the
+    /// workspace, in order. Defaults to empty, to have all of them.
+    pub layouts: Vec<&'static str>,
+    /// Name of the layout the workspace starts with, overriding
+    /// `ScreenConfig::layout`. Defaults to `None`, to start with the first
+    /// one.
+    pub default_layout: Option<&'static str>,
+}
+
+impl WorkspaceConfig {
+    /// Creates settings for a workspace without overriding anything.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use le_petit_lapin::*;
+    /// use le_petit_lapin::config::*;
+    /// let mut lapin = Lapin::connect();
+    /// // the browser workspace is only maximized or floating.
+    /// let mut www = WorkspaceConfig::new(1);
+    /// www.layouts = vec!["Maximized", "Floating"];
+    /// // the terminals workspace starts tiled but may be maximized.
+    /// let mut terminals = WorkspaceConfig::new(0);
+    /// terminals.default_layout = Some("Tiling");
+    /// lapin.config.workspace_configs = vec![www, terminals];
+    /// ```
+    pub fn new(workspace: usize) -> Self {
+        WorkspaceConfig {
+            workspace,
+            layouts: vec![],
+            default_layout: None,
+        }
+    }
+}
 
 /// General configuration of the window manager.
 pub struct Config {
@@ -17,21 +114,76 @@ pub struct Config {
     /// Border color of focused windows in the form ARGB. Defaults to
     /// `0xffffffff`.
     pub border_color_focus: u32,
+    /// Border color of windows demanding attention in the form ARGB.
+    /// Defaults to `0xffff0000`.
+    pub border_color_urgent: u32,
     /// Border width of ool windows. Defaults to `4`.
     pub border_width: u32,
     /// Outer gaps, usefull to reserve space for a bar. In the format
-    /// (top, right, bottom, left). Defaults to (0, 0, 0, 0).
+    /// (top, right, bottom, left). Docks (bars that set
+    /// `_NET_WM_STRUT_PARTIAL`) reserve their space automatically, so each
+    /// side uses the biggest of this and what docks ask for. May be
+    /// overridden per screen with `screens`. Defaults to (0, 0, 0, 0).
     pub reserved_space: (u16, u16, u16, u16),
-    /// Layouts to use. Defaults to the three built-in layouts with
-    /// default configs.
+    /// Settings of specific screens. When more than one matches a screen,
+    /// the first is used. Empty by default.
+    pub screens: Vec<ScreenConfig>,
+    /// Layouts to use. Every workspace gets its own copy of them, so
+    /// changing a layout at runtime only affects one workspace. Defaults to
+    /// the three built-in layouts with default configs.
     pub layouts: Vec<Box<dyn Layout>>,
+    /// Settings of specific workspaces: which layouts they have and which
+    /// one they start with. When more than one matches a workspace, the
+    /// first is used. Empty by default.
+    pub workspace_configs: Vec<WorkspaceConfig>,
     /// Rules to apply to windows on spawn. No rule by default.
     pub rules: Vec<Rule>,
+    /// Closures called, in order, when a window is managed, after rules
+    /// placed it. If one of them returns `false`, the others aren't
+    /// called and the window is left unmanaged. No hook by default.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use le_petit_lapin::*;
+    /// let mut lapin = Lapin::connect();
+    /// lapin.config.manage_hooks.push(Box::new(|wm, window| {
+    ///     // tell when a window shows up without getting the focus.
+    ///     if wm.get_focused_window() != Some(window) {
+    ///         Lapin::spawn("notify-send 'new window in the background'");
+    ///     }
+    ///     true
+    /// }));
+    /// ```
+    pub manage_hooks: Vec<ManageHook>,
+    /// Types of windows that always float, centered over the window they
+    /// belong to, or over the screen. Rules may still place them elsewhere.
+    /// Defaults to dialogs, utilities and splash screens.
+    pub float_window_types: Vec<WindowType>,
+    /// If transient windows (e.g., file pickers) always float, centered
+    /// over the window they belong to. Defaults to `true`.
+    pub float_transients: bool,
     /// If hovering a window should raise it (make it above other
     /// windows). If `false`, it'll just make it focused. Changing the
     /// focus with the keyboard always raise the window. Defaults to
     /// `true`.
     pub mouse_raises_window: bool,
+    /// How long to wait for a window asked to close by
+    /// `Lapin::close_focused()` before killing its client. `None` to never
+    /// kill it automatically, which is the default, as the client may be
+    /// asking the user to save their work.
+    pub close_timeout: Option<time::Duration>,
+    /// If programs spawned with `Lapin::spawn()` and friends (e.g. by
+    /// keybinds or hooks) should have their windows placed in the workspace
+    /// they were spawned from, even if the focus changed to another one
+    /// before they showed up. Programs spawned with
+    /// `Lapin::spawn_on()` are always placed in the requested workspace.
+    /// Defaults to `false`.
+    pub spawn_on_origin: bool,
+    /// If the window manager should listen for commands on a socket. See
+    /// the `ipc` module. Any program of the user may send commands, so it
+    /// defaults to `true` only if `XDG_RUNTIME_DIR` is set, as that
+    /// directory is private to the user.
+    pub ipc: bool,
 }
 
 impl Config {
@@ -41,15 +193,24 @@ impl Config {
             mouse_mod: &["Super"],
             border_color: 0xff000000,
             border_color_focus: 0xffffffff,
+            border_color_urgent: 0xffff0000,
             border_width: 4,
             reserved_space: (0, 0, 0, 0),
+            screens: vec![],
             mouse_raises_window: true,
             layouts: vec![
                 Box::new(Tiling::new()),
                 Box::new(Maximized::new()),
                 Box::new(Floating::new()),
             ],
+            workspace_configs: vec![],
             rules: vec![],
+            manage_hooks: vec![],
+            float_window_types: vec![WindowType::Dialog, WindowType::Utility, WindowType::Splash],
+            float_transients: true,
+            close_timeout: None,
+            spawn_on_origin: false,
+            ipc: std::env::var_os("XDG_RUNTIME_DIR").is_some(),
         }
     }
 }
@@ -0,0 +1,158 @@
+//! Docks (e.g. bars and panels) and the space they reserve in the screens.
+//!
+//! Windows of type `_NET_WM_WINDOW_TYPE_DOCK` are mapped but not managed:
+//! they don't get borders, focus nor a place in the layouts. The space they
+//! ask with `_NET_WM_STRUT_PARTIAL` (or the older `_NET_WM_STRUT`) is
+//! reserved in the screens they touch, and published in `_NET_WORKAREA`.
+
+use crate::Lapin;
+use xcb::x;
+
+impl Lapin {
+    /// Starts tracking a dock, mapping it.
+    pub(crate) fn manage_dock(&mut self, window: x::Window) {
+        self.x_connection.send_request(&x::ChangeWindowAttributes {
+            window,
+            value_list: &[x::Cw::EventMask(
+                x::EventMask::PROPERTY_CHANGE | x::EventMask::STRUCTURE_NOTIFY,
+            )],
+        });
+        self.x_connection.send_request(&x::MapWindow { window });
+        if !self.docks.contains(&window) {
+            self.docks.push(window);
+        }
+        self.update_struts();
+    }
+
+    /// Stops tracking a dock that went away. Does nothing if the window
+    /// isn't a dock.
+    pub(crate) fn unmanage_dock(&mut self, window: x::Window) {
+        if !self.docks.contains(&window) {
+            return;
+        }
+        self.docks.retain(|dock| *dock != window);
+        self.update_struts();
+    }
+
+    /// Returns if a window is a dock being tracked.
+    pub(crate) fn is_dock(&self, window: x::Window) -> bool {
+        self.docks.contains(&window)
+    }
+
+    /// Recomputes the space reserved by docks in every screen, re-tiling
+    /// the screens that changed, and updates `_NET_WORKAREA`.
+    pub(crate) fn update_struts(&mut self) {
+        let (root_width, root_height) = self.root_size();
+        let struts: Vec<[u32; 12]> = self
+            .docks
+            .iter()
+            .filter_map(|dock| self.get_strut(*dock, root_width, root_height))
+            .collect();
+
+        for s in 0..self.screens.len() {
+            let screen = &self.screens[s];
+            let (sx, sy) = (screen.x as i64, screen.y as i64);
+            let (sw, sh) = (screen.width as i64, screen.height as i64);
+            // how far the strut goes inside the screen, if it overlaps
+            // with it in the other axis.
+            let inside = |depth: i64, start: u32, end: u32, from: i64, length: i64| {
+                if depth > 0 && (start as i64) < from + length && (end as i64) >= from {
+                    depth.min(length) as u16
+                } else {
+                    0
+                }
+            };
+            let mut reserved = (0, 0, 0, 0);
+            for strut in &struts {
+                let [left, right, top, bottom, ly0, ly1, ry0, ry1, tx0, tx1, bx0, bx1] = *strut;
+                let top = inside(top as i64 - sy, tx0, tx1, sx, sw);
+                let right = inside(sx + sw - (root_width - right as i64), ry0, ry1, sy, sh);
+                let bottom = inside(sy + sh - (root_height - bottom as i64), bx0, bx1, sx, sw);
+                let left = inside(left as i64 - sx, ly0, ly1, sy, sh);
+                reserved.0 = top.max(reserved.0);
+                reserved.1 = right.max(reserved.1);
+                reserved.2 = bottom.max(reserved.2);
+                reserved.3 = left.max(reserved.3);
+            }
+            if self.screens[s].reserved_space != reserved {
+                self.screens[s].reserved_space = reserved;
+                let screen = &self.screens[s];
+                if screen.workspaces[screen.current_wk].respect_reserved_space {
+                    self.reload_screen(s);
+                }
+            }
+        }
+
+        // the work area is the same for every desktop: the whole root
+        // window without what docks reserve on its edges.
+        let side = |n: usize| struts.iter().map(|strut| strut[n]).max().unwrap_or(0) as i64;
+        let (left, right, top, bottom) = (side(0), side(1), side(2), side(3));
+        let workarea = [
+            left as u32,
+            top as u32,
+            (root_width - left - right).max(0) as u32,
+            (root_height - top - bottom).max(0) as u32,
+        ];
+        let data: Vec<u32> = workarea
+            .iter()
+            .copied()
+            .cycle()
+            .take(workarea.len() * self.config.workspaces.len())
+            .collect();
+        self.x_connection.send_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window: self.root,
+            property: self.atoms.net_workarea,
+            r#type: x::ATOM_CARDINAL,
+            data: &data,
+        });
+        self.x_connection.flush().ok();
+    }
+
+    /// Reads the strut of a dock, as the 12 values of
+    /// `_NET_WM_STRUT_PARTIAL`. A `_NET_WM_STRUT` spans the whole edge.
+    fn get_strut(&self, window: x::Window, root_width: i64, root_height: i64) -> Option<[u32; 12]> {
+        for (property, length) in [
+            (self.atoms.net_wm_strut_partial, 12),
+            (self.atoms.net_wm_strut, 4),
+        ] {
+            let cookie = self.x_connection.send_request(&x::GetProperty {
+                delete: false,
+                window,
+                property,
+                r#type: x::ATOM_CARDINAL,
+                long_offset: 0,
+                long_length: length,
+            });
+            let Ok(reply) = self.x_connection.wait_for_reply(cookie) else {
+                continue;
+            };
+            if reply.r#type() != x::ATOM_CARDINAL || reply.format() != 32 {
+                continue;
+            }
+            let values = reply.value::<u32>();
+            if values.len() < length as usize {
+                continue;
+            }
+            let mut strut = [0; 12];
+            strut[..values.len().min(12)].copy_from_slice(&values[..values.len().min(12)]);
+            if length == 4 {
+                let (last_x, last_y) = ((root_width - 1) as u32, (root_height - 1) as u32);
+                strut[4..].copy_from_slice(&[0, last_y, 0, last_y, 0, last_x, 0, last_x]);
+            }
+            return Some(strut);
+        }
+        None
+    }
+
+    /// Returns the size of the root window.
+    fn root_size(&self) -> (i64, i64) {
+        let cookie = self.x_connection.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(self.root),
+        });
+        match self.x_connection.wait_for_reply(cookie) {
+            Ok(reply) => (reply.width() as i64, reply.height() as i64),
+            Err(_) => (0, 0),
+        }
+    }
+}
@@ -0,0 +1,384 @@
+//! Handling of EWMH client states (`_NET_WM_STATE`) requested by clients.
+
+use crate::{FullscreenRestore, Lapin};
+use xcb::x;
+use xcb::XidNew;
+
+/// Actions of a `_NET_WM_STATE` client message.
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+const NET_WM_STATE_TOGGLE: u32 = 2;
+
+impl Lapin {
+    /// Handles a client message sent to the root window.
+    pub(crate) fn client_message(&mut self, ev: x::ClientMessageEvent) {
+        let x::ClientMessageData::Data32(data) = ev.data() else {
+            return;
+        };
+        if ev.r#type() == self.atoms.net_wm_state {
+            // a single message may change two properties at once, e.g.
+            // both maximized states.
+            for state in [data[1], data[2]] {
+                if state != 0 {
+                    self.net_wm_state_request(ev.window(), data[0], x::Atom::new(state));
+                }
+            }
+            self.x_connection.flush().ok();
+        }
+    }
+
+    /// Applies a `_NET_WM_STATE` change requested by a client.
+    fn net_wm_state_request(&mut self, window: x::Window, action: u32, state: x::Atom) {
+        if self.window_location(window).is_none() {
+            return;
+        }
+        let has = self.has_net_wm_state(window, state);
+        let enable = match action {
+            NET_WM_STATE_REMOVE => false,
+            NET_WM_STATE_ADD => true,
+            NET_WM_STATE_TOGGLE => !has,
+            _ => return,
+        };
+        if enable == has {
+            return;
+        }
+
+        if state == self.atoms.net_wm_state_fullscreen {
+            self.set_fullscreen(window, enable);
+        } else if state == self.atoms.net_wm_state_maximized_vert {
+            self.set_maximized(window, true, enable);
+        } else if state == self.atoms.net_wm_state_maximized_horz {
+            self.set_maximized(window, false, enable);
+        } else if state == self.atoms.net_wm_state_above {
+            self.set_stacking(window, true, enable);
+        } else if state == self.atoms.net_wm_state_below {
+            self.set_stacking(window, false, enable);
+        } else if state == self.atoms.net_wm_state_sticky {
+            self.set_sticky(window, enable);
+        } else if state == self.atoms.net_wm_state_hidden {
+            self.set_hidden(window, enable);
+        } else if state == self.atoms.net_wm_state_demands_attention {
+            self.set_urgent(window, enable);
+        }
+    }
+
+    /// Makes a window fullscreen on its screen, or restores it to where it
+    /// was before: its position in the stack if tiled, or its geometry if
+    /// ool.
+    pub(crate) fn set_fullscreen(&mut self, window: x::Window, enable: bool) {
+        let Some((s, k, w, ool)) = self.window_location(window) else {
+            return;
+        };
+        if enable == self.fullscreen.contains_key(&window) {
+            self.set_net_wm_state(window, self.atoms.net_wm_state_fullscreen, enable);
+            return;
+        }
+        let visible = self.screens[s].current_wk == k;
+        let workspace = &self.screens[s].workspaces[k];
+        let focused = workspace.ool_focus == ool && workspace.focused == Some(w);
+
+        if enable {
+            let cookie = self.x_connection.send_request(&x::GetGeometry {
+                drawable: x::Drawable::Window(window),
+            });
+            let Ok(geometry) = self.x_connection.wait_for_reply(cookie) else {
+                return;
+            };
+            self.fullscreen.insert(
+                window,
+                FullscreenRestore {
+                    ool,
+                    index: w,
+                    geometry: (
+                        geometry.x(),
+                        geometry.y(),
+                        geometry.width(),
+                        geometry.height(),
+                        geometry.border_width(),
+                    ),
+                },
+            );
+            if !ool {
+                let workspace = &mut self.screens[s].workspaces[k];
+                workspace.remove_window(window);
+                workspace.insert_window(window, true);
+                if focused {
+                    workspace.focused = Some(0);
+                    workspace.ool_focus = true;
+                }
+                if visible {
+                    self.reload_screen(s);
+                }
+            }
+            let screen = &self.screens[s];
+            self.x_connection.send_request(&x::ConfigureWindow {
+                window,
+                value_list: &[
+                    x::ConfigWindow::X(screen.x as i32),
+                    x::ConfigWindow::Y(screen.y as i32),
+                    x::ConfigWindow::Width(screen.width as u32),
+                    x::ConfigWindow::Height(screen.height as u32),
+                    x::ConfigWindow::BorderWidth(0),
+                    x::ConfigWindow::StackMode(x::StackMode::Above),
+                ],
+            });
+        } else {
+            let Some(restore) = self.fullscreen.remove(&window) else {
+                return;
+            };
+            if restore.ool {
+                let (x, y, width, height, border) = restore.geometry;
+                self.x_connection.send_request(&x::ConfigureWindow {
+                    window,
+                    value_list: &[
+                        x::ConfigWindow::X(x as i32),
+                        x::ConfigWindow::Y(y as i32),
+                        x::ConfigWindow::Width(width as u32),
+                        x::ConfigWindow::Height(height as u32),
+                        x::ConfigWindow::BorderWidth(border as u32),
+                    ],
+                });
+            } else {
+                let workspace = &mut self.screens[s].workspaces[k];
+                workspace.remove_window(window);
+                let index = restore.index.min(workspace.windows.len());
+                workspace.insert_window_at(index, window, false);
+                if focused {
+                    workspace.focused = Some(index);
+                    workspace.ool_focus = false;
+                }
+                let border = workspace.current_layout().border_width() as u32;
+                self.x_connection.send_request(&x::ConfigureWindow {
+                    window,
+                    value_list: &[x::ConfigWindow::BorderWidth(
+                        self.border_width_for(window, border),
+                    )],
+                });
+                if visible {
+                    self.reload_screen(s);
+                }
+            }
+        }
+        self.set_net_wm_state(window, self.atoms.net_wm_state_fullscreen, enable);
+        self.x_connection.flush().ok();
+    }
+
+    /// Maximizes a window vertically or horizontally. Only works with
+    /// windows that are free to move, i.e., ool ones or the ones in a
+    /// layout that allows motions.
+    fn set_maximized(&mut self, window: x::Window, vertical: bool, enable: bool) {
+        let Some((s, k, _, ool)) = self.window_location(window) else {
+            return;
+        };
+        if !ool
+            && !self.screens[s].workspaces[k]
+                .current_layout()
+                .allow_motions()
+        {
+            return;
+        }
+        let cookie = self.x_connection.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        });
+        let Ok(geometry) = self.x_connection.wait_for_reply(cookie) else {
+            return;
+        };
+        let (width, height, x, y) = self.layout_coordinates(s, k);
+        let borders = geometry.border_width() * 2;
+
+        let saved = self.maximized.entry(window).or_default();
+        let list = if enable {
+            if vertical {
+                saved.1 = Some((geometry.y(), geometry.height()));
+                [
+                    x::ConfigWindow::Y(y as i32),
+                    x::ConfigWindow::Height(height.saturating_sub(borders).max(1) as u32),
+                ]
+            } else {
+                saved.0 = Some((geometry.x(), geometry.width()));
+                [
+                    x::ConfigWindow::X(x as i32),
+                    x::ConfigWindow::Width(width.saturating_sub(borders).max(1) as u32),
+                ]
+            }
+        } else {
+            let restore = if vertical {
+                saved.1.take()
+            } else {
+                saved.0.take()
+            };
+            let Some((position, length)) = restore else {
+                self.set_net_wm_state(window, self.maximized_atom(vertical), false);
+                return;
+            };
+            if vertical {
+                [
+                    x::ConfigWindow::Y(position as i32),
+                    x::ConfigWindow::Height(length as u32),
+                ]
+            } else {
+                [
+                    x::ConfigWindow::X(position as i32),
+                    x::ConfigWindow::Width(length as u32),
+                ]
+            }
+        };
+        if saved.0.is_none() && saved.1.is_none() {
+            self.maximized.remove(&window);
+        }
+        self.x_connection.send_request(&x::ConfigureWindow {
+            window,
+            value_list: &list,
+        });
+        self.set_net_wm_state(window, self.maximized_atom(vertical), enable);
+    }
+
+    fn maximized_atom(&self, vertical: bool) -> x::Atom {
+        if vertical {
+            self.atoms.net_wm_state_maximized_vert
+        } else {
+            self.atoms.net_wm_state_maximized_horz
+        }
+    }
+
+    /// Keeps a window above or below the others.
+    fn set_stacking(&mut self, window: x::Window, above: bool, enable: bool) {
+        let (state, other, mode) = if above {
+            (
+                self.atoms.net_wm_state_above,
+                self.atoms.net_wm_state_below,
+                x::StackMode::Above,
+            )
+        } else {
+            (
+                self.atoms.net_wm_state_below,
+                self.atoms.net_wm_state_above,
+                x::StackMode::Below,
+            )
+        };
+        if enable {
+            self.x_connection.send_request(&x::ConfigureWindow {
+                window,
+                value_list: &[x::ConfigWindow::StackMode(mode)],
+            });
+            self.set_net_wm_state(window, other, false);
+        }
+        self.set_net_wm_state(window, state, enable);
+    }
+
+    /// Sticky windows follow the workspace changes of their screen.
+    pub(crate) fn set_sticky(&mut self, window: x::Window, enable: bool) {
+        self.sticky.retain(|w| *w != window);
+        if enable {
+            self.sticky.push(window);
+        }
+        self.set_net_wm_state(window, self.atoms.net_wm_state_sticky, enable);
+    }
+
+    /// Moves the sticky windows of a workspace of the current screen to
+    /// another one. Called before changing workspaces.
+    pub(crate) fn move_sticky_windows(&mut self, from: usize, to: usize) {
+        for window in self.sticky.clone() {
+            let screen = self.current_screen_mut();
+            let Some(ool) = screen.workspaces[from].remove_window(window) else {
+                continue;
+            };
+            screen.workspaces[to].insert_window(window, ool);
+            self.x_connection.send_request(&x::ChangeProperty {
+                mode: x::PropMode::Replace,
+                window,
+                property: self.atoms.net_wm_desktop,
+                r#type: x::ATOM_CARDINAL,
+                data: &[to as u32],
+            });
+        }
+    }
+
+    /// Hidden windows are kept unmapped until the state is removed. They
+    /// leave their place in the layout and can't be focused meanwhile.
+    fn set_hidden(&mut self, window: x::Window, enable: bool) {
+        let Some((s, k, w, ool)) = self.window_location(window) else {
+            return;
+        };
+        let was_hidden = self.hidden.contains(&window);
+        self.hidden.retain(|w| *w != window);
+        if enable {
+            self.hidden.push(window);
+            self.x_connection.send_request(&x::UnmapWindow { window });
+            let workspace = &self.screens[s].workspaces[k];
+            if !was_hidden && workspace.focused == Some(w) && workspace.ool_focus == ool {
+                self.focus_off_hidden(s, k);
+            }
+        } else if self.screens[s].current_wk == k {
+            self.x_connection.send_request(&x::MapWindow { window });
+        }
+        if !ool && was_hidden != enable && self.screens[s].current_wk == k {
+            self.reload_screen(s);
+        }
+        self.set_net_wm_state(window, self.atoms.net_wm_state_hidden, enable);
+    }
+
+    /// Marks a window as demanding attention. The focused window never
+    /// demands attention, as it already has it.
+    pub(crate) fn set_urgent(&mut self, window: x::Window, enable: bool) {
+        let enable = enable && self.get_focused_window() != Some(window);
+        self.urgent.retain(|w| *w != window);
+        if enable {
+            self.urgent.push(window);
+        }
+        if self.get_focused_window() != Some(window) {
+            self.restore_border(window);
+        }
+        self.set_net_wm_state(window, self.atoms.net_wm_state_demands_attention, enable);
+    }
+
+    /// Returns the list of atoms stored in a property of a window.
+    pub(crate) fn get_atom_list_property(
+        &self,
+        window: x::Window,
+        property: x::Atom,
+    ) -> Vec<x::Atom> {
+        let cookie = self.x_connection.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property,
+            r#type: x::ATOM_ATOM,
+            long_offset: 0,
+            long_length: 1024,
+        });
+        if let Ok(reply) = self.x_connection.wait_for_reply(cookie) {
+            if reply.r#type() == x::ATOM_ATOM {
+                return reply.value::<x::Atom>().to_vec();
+            }
+        }
+        Vec::new()
+    }
+
+    /// Checks if a window has a state in its `_NET_WM_STATE`.
+    pub(crate) fn has_net_wm_state(&self, window: x::Window, state: x::Atom) -> bool {
+        self.get_atom_list_property(window, self.atoms.net_wm_state)
+            .contains(&state)
+    }
+
+    /// Adds or removes a state from the `_NET_WM_STATE` of a window,
+    /// leaving the other states untouched.
+    pub(crate) fn set_net_wm_state(&self, window: x::Window, state: x::Atom, enable: bool) {
+        let mut states = self.get_atom_list_property(window, self.atoms.net_wm_state);
+        let has = states.contains(&state);
+        if enable == has {
+            return;
+        }
+        if enable {
+            states.push(state);
+        } else {
+            states.retain(|s| *s != state);
+        }
+        self.x_connection.send_request(&x::ChangeProperty {
+            mode: x::PropMode::Replace,
+            window,
+            property: self.atoms.net_wm_state,
+            r#type: x::ATOM_ATOM,
+            data: &states,
+        });
+    }
+}
@@ -0,0 +1,489 @@
+//! Control of the window manager from other programs.
+//!
+//! Lapin listens on a Unix domain socket for commands, one per line, like
+//! `goto_workspace 2` or `next_layout`. Each command is answered with a
+//! line with `ok` or `error: <reason>`. Workspaces are indexed from 0, as
+//! in the `Lapin` methods. The available commands are:
+//!
+//! - `goto_workspace <workspace>`
+//! - `send_window_to_workspace <workspace>`
+//! - `next_layout` and `prev_layout`
+//! - `nextwin` and `prevwin`
+//! - `change_master`
+//! - `inc_master` and `dec_master`
+//! - `killfocused` and `close_focused`
+//! - `toggle_ool`
+//! - `fullscreen` and `toggle_fullscreen`
+//! - `toggle_reserved_space`
+//! - `next_screen` and `prev_screen`
+//! - `send_window_to_next_screen` and `send_window_to_prev_screen`
+//! - `state`, which replies with a line with `Lapin::state_snapshot()` as
+//!   JSON before the `ok`
+//! - `subscribe`, after which the client receives an `Event` per line, as
+//!   JSON, until it disconnects.
+//!
+//! The `lapinctl` binary shipped with this crate sends its arguments as a
+//! command, e.g. `lapinctl goto_workspace 2`. Running `lapinctl subscribe`
+//! prints the events as they happen, so it can be used to feed a bar.
+
+use crate::Lapin;
+use serde::Serialize;
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use xcb::Xid;
+
+/// Returns the path of the socket. It's the value of `LAPIN_SOCKET` if set,
+/// otherwise `lapin-<display>.sock` inside `XDG_RUNTIME_DIR`, or inside a
+/// private `/tmp/lapin-<uid>` directory if there's no runtime dir.
+pub fn socket_path() -> PathBuf {
+    if let Some(path) = env::var_os("LAPIN_SOCKET") {
+        return PathBuf::from(path);
+    }
+    let display = env::var("DISPLAY").unwrap_or_default().replace('/', "_");
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join(format!("lapin-{display}.sock")),
+        None => fallback_dir().join(format!("lapin-{display}.sock")),
+    }
+}
+
+/// The directory of the socket when there's no `XDG_RUNTIME_DIR`.
+fn fallback_dir() -> PathBuf {
+    let uid = unsafe { libc::getuid() };
+    PathBuf::from(format!("/tmp/lapin-{uid}"))
+}
+
+/// Creates the directory of the socket in `/tmp`, only accessible by the
+/// user. Fails if it already exists but someone else could use it.
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    match fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir()
+        || metadata.uid() != unsafe { libc::getuid() }
+        || metadata.mode() & 0o077 != 0
+    {
+        return Err(std::io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!("{} is not a private directory", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Removes a socket left by a window manager that died. Refuses to remove
+/// anything else, or a socket of another user.
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() || metadata.uid() != unsafe { libc::getuid() } {
+        return Err(std::io::Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket of ours", path.display()),
+        ));
+    }
+    fs::remove_file(path)
+}
+
+/// An event sent to subscribed clients, serialized as a JSON object with
+/// its name in `event`, e.g.
+/// `{"event":"workspace","screen":0,"workspace":2,"name":"3"}`. Windows are
+/// sent as their X ids.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The current workspace of a screen changed.
+    Workspace {
+        screen: usize,
+        workspace: usize,
+        name: String,
+    },
+    /// The focus changed. `window` is `None` if nothing is focused.
+    Focus {
+        window: Option<u32>,
+        title: Option<String>,
+    },
+    /// The layout of a workspace changed.
+    Layout {
+        screen: usize,
+        workspace: usize,
+        layout: String,
+    },
+    /// A window started being managed. Sent after the manage hooks ran,
+    /// so windows they veto are never announced.
+    Manage {
+        window: u32,
+        screen: usize,
+        workspace: usize,
+    },
+    /// A window stopped being managed.
+    Unmanage { window: u32 },
+    /// The focused screen changed.
+    Screen { screen: usize },
+    /// The title of a managed window changed.
+    Title { window: u32, title: String },
+}
+
+/// How much output may wait for a client to read it before the client is
+/// dropped.
+const MAX_OUTPUT: usize = 1 << 20;
+
+/// A connected client, what it sent that isn't a full line yet, and what
+/// it wasn't able to read yet.
+struct Client {
+    stream: UnixStream,
+    buffer: Vec<u8>,
+    output: Vec<u8>,
+    /// The client won't send anything else.
+    eof: bool,
+    /// The client receives events.
+    subscribed: bool,
+    /// The connection can't be used anymore.
+    broken: bool,
+}
+
+impl Client {
+    /// Sends a line. Never blocks: what the client can't read now is kept
+    /// to be sent later, and a client that falls too far behind is dropped.
+    fn send(&mut self, line: &str) {
+        if self.broken {
+            return;
+        }
+        self.output.extend_from_slice(line.as_bytes());
+        self.output.push(b'\n');
+        self.flush();
+        if self.output.len() > MAX_OUTPUT {
+            self.broken = true;
+        }
+    }
+
+    /// Writes as much of the pending output as the client can read now.
+    fn flush(&mut self) {
+        while !self.output.is_empty() && !self.broken {
+            match self.stream.write(&self.output) {
+                Ok(0) => self.broken = true,
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(_) => self.broken = true,
+            }
+        }
+    }
+}
+
+/// The socket the window manager listens on, with its clients.
+pub(crate) struct IpcServer {
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<Client>,
+}
+
+impl IpcServer {
+    /// Listens on `socket_path()`. Fails if another window manager is
+    /// already listening on it.
+    pub(crate) fn bind() -> std::io::Result<Self> {
+        let path = socket_path();
+        if UnixStream::connect(&path).is_ok() {
+            return Err(std::io::Error::new(
+                ErrorKind::AddrInUse,
+                format!("{} is already in use", path.display()),
+            ));
+        }
+        if path.parent() == Some(fallback_dir().as_path()) {
+            create_private_dir(&fallback_dir())?;
+        }
+        remove_stale_socket(&path)?;
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        Ok(IpcServer {
+            listener,
+            path,
+            clients: Vec::new(),
+        })
+    }
+
+    /// Returns the file descriptors to poll, with the poll events to wait
+    /// for: new connections, commands, and clients ready to read pending
+    /// output.
+    pub(crate) fn fds(&self) -> Vec<(RawFd, libc::c_short)> {
+        let mut fds = vec![(self.listener.as_raw_fd(), libc::POLLIN)];
+        for client in self.clients.iter().filter(|client| !client.broken) {
+            let mut events = 0;
+            if !client.eof {
+                events |= libc::POLLIN;
+            }
+            if !client.output.is_empty() {
+                events |= libc::POLLOUT;
+            }
+            if events != 0 {
+                fds.push((client.stream.as_raw_fd(), events));
+            }
+        }
+        fds
+    }
+
+    /// Accepts new clients and returns the commands received, with the
+    /// index of the client that sent each one. Never blocks.
+    fn receive(&mut self) -> Vec<(usize, String)> {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.clients.push(Client {
+                    stream,
+                    buffer: Vec::new(),
+                    output: Vec::new(),
+                    eof: false,
+                    subscribed: false,
+                    broken: false,
+                });
+            }
+        }
+
+        let mut commands = Vec::new();
+        for (index, client) in self.clients.iter_mut().enumerate() {
+            client.flush();
+            if client.eof || client.broken {
+                continue;
+            }
+            let mut buffer = [0; 1024];
+            loop {
+                match client.stream.read(&mut buffer) {
+                    Ok(0) => client.eof = true,
+                    Ok(n) => {
+                        client.buffer.extend_from_slice(&buffer[..n]);
+                        continue;
+                    }
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(_) => client.broken = true,
+                }
+                break;
+            }
+            while let Some(end) = client.buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = client.buffer.drain(..=end).collect();
+                commands.push((index, String::from_utf8_lossy(&line).trim().to_string()));
+            }
+            if client.eof {
+                // a last command may come without a newline.
+                let line = std::mem::take(&mut client.buffer);
+                let line = String::from_utf8_lossy(&line).trim().to_string();
+                if !line.is_empty() {
+                    commands.push((index, line));
+                }
+            }
+        }
+        commands
+    }
+
+    /// Drops clients that are done: the ones with a broken connection,
+    /// and the ones that won't send commands, aren't subscribed and have
+    /// read all their output.
+    fn drop_closed(&mut self) {
+        self.clients.retain(|client| {
+            !client.broken && (client.subscribed || !client.eof || !client.output.is_empty())
+        });
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.path).ok();
+    }
+}
+
+impl Lapin {
+    /// Runs the commands sent by clients. Never blocks.
+    pub(crate) fn handle_ipc(&mut self) {
+        let commands = match &mut self.ipc {
+            Some(ipc) => ipc.receive(),
+            None => return,
+        };
+        for (client, command) in commands {
+            let reply = match &command[..] {
+                "subscribe" => {
+                    if let Some(ipc) = &mut self.ipc {
+                        ipc.clients[client].subscribed = true;
+                    }
+                    Ok(None)
+                }
+                "state" => Ok(Some(self.state_snapshot().to_json())),
+                command => self.run_command(command).map(|_| None),
+            };
+            // clients are only dropped below, so the index is still valid.
+            if let Some(ipc) = &mut self.ipc {
+                let client = &mut ipc.clients[client];
+                match reply {
+                    Ok(output) => {
+                        if let Some(output) = output {
+                            client.send(&output);
+                        }
+                        client.send("ok");
+                    }
+                    Err(e) => client.send(&format!("error: {e}")),
+                }
+            }
+        }
+        if let Some(ipc) = &mut self.ipc {
+            ipc.drop_closed();
+        }
+    }
+
+    /// Returns if there's any client subscribed to events, so events that
+    /// are expensive to build can be skipped.
+    pub(crate) fn has_subscribers(&self) -> bool {
+        self.ipc
+            .as_ref()
+            .is_some_and(|ipc| ipc.clients.iter().any(|client| client.subscribed))
+    }
+
+    /// Sends an event to the subscribed clients.
+    pub(crate) fn emit(&mut self, event: Event) {
+        if !self.has_subscribers() {
+            return;
+        }
+        let Some(ipc) = &mut self.ipc else {
+            return;
+        };
+        let Ok(line) = serde_json::to_string(&event) else {
+            return;
+        };
+        for client in ipc.clients.iter_mut().filter(|client| client.subscribed) {
+            client.send(&line);
+        }
+    }
+
+    /// Sends a focus event with the currently focused window.
+    pub(crate) fn emit_focus(&mut self) {
+        if !self.has_subscribers() {
+            return;
+        }
+        let window = self.get_focused_window();
+        let title = window.and_then(|window| self.get_title(window));
+        self.emit(Event::Focus {
+            window: window.map(|window| window.resource_id()),
+            title,
+        });
+    }
+
+    /// Sends a workspace event with the current workspace of a screen.
+    pub(crate) fn emit_workspace(&mut self, screen: usize) {
+        let workspace = self.screens[screen].current_wk;
+        let name = self.screens[screen].workspaces[workspace].name.to_string();
+        self.emit(Event::Workspace {
+            screen,
+            workspace,
+            name,
+        });
+    }
+
+    /// Runs a single command.
+    fn run_command(&mut self, command: &str) -> Result<(), String> {
+        let mut words = command.split_whitespace();
+        let name = words.next().ok_or("empty command")?;
+        let arg = words.next();
+        if words.next().is_some() {
+            return Err(format!("too many arguments to {name}"));
+        }
+        let no_arg = || match arg {
+            Some(_) => Err(format!("{name} takes no arguments")),
+            None => Ok(()),
+        };
+
+        match name {
+            "goto_workspace" => self.goto_workspace(self.workspace_arg(name, arg)?),
+            "send_window_to_workspace" => {
+                self.send_window_to_workspace(self.workspace_arg(name, arg)?)
+            }
+            "next_layout" => {
+                no_arg()?;
+                self.next_layout()
+            }
+            "prev_layout" => {
+                no_arg()?;
+                self.prev_layout()
+            }
+            "nextwin" => {
+                no_arg()?;
+                self.nextwin()
+            }
+            "prevwin" => {
+                no_arg()?;
+                self.prevwin()
+            }
+            "change_master" => {
+                no_arg()?;
+                self.change_master()
+            }
+            "inc_master" => {
+                no_arg()?;
+                self.inc_master()
+            }
+            "dec_master" => {
+                no_arg()?;
+                self.dec_master()
+            }
+            "killfocused" => {
+                no_arg()?;
+                self.killfocused()
+            }
+            "close_focused" => {
+                no_arg()?;
+                self.close_focused()
+            }
+            "toggle_ool" => {
+                no_arg()?;
+                self.toggle_ool()
+            }
+            "fullscreen" => {
+                no_arg()?;
+                self.fullscreen()
+            }
+            "toggle_fullscreen" => {
+                no_arg()?;
+                self.toggle_fullscreen()
+            }
+            "toggle_reserved_space" => {
+                no_arg()?;
+                self.toggle_reserved_space()
+            }
+            "next_screen" => {
+                no_arg()?;
+                self.next_screen()
+            }
+            "prev_screen" => {
+                no_arg()?;
+                self.prev_screen()
+            }
+            "send_window_to_next_screen" => {
+                no_arg()?;
+                self.send_window_to_next_screen()
+            }
+            "send_window_to_prev_screen" => {
+                no_arg()?;
+                self.send_window_to_prev_screen()
+            }
+            other => return Err(format!("unknown command {other}")),
+        }
+        self.x_connection.flush().ok();
+        Ok(())
+    }
+
+    /// Parses the workspace argument of a command.
+    fn workspace_arg(&self, name: &str, arg: Option<&str>) -> Result<usize, String> {
+        let arg = arg.ok_or(format!("{name} requires a workspace"))?;
+        match arg.parse() {
+            Ok(workspace) if workspace < self.current_screen().workspaces.len() => Ok(workspace),
+            _ => Err(format!("no such workspace {arg}")),
+        }
+    }
+}
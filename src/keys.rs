@@ -147,7 +147,9 @@ impl KeybindSet {
     }
 }
 
-/// Creates a closure suitable to use in keybinds.
+/// Creates a closure suitable to use in keybinds. The value of the
+/// expression is discarded, so functions returning something, like
+/// `Lapin::spawn`, can be used directly.
 ///
 /// # Example
 /// ```no_run
@@ -173,9 +175,13 @@ impl KeybindSet {
 #[macro_export]
 macro_rules! lazy {
     ($callback:expr) => {
-        Box::new(|_: &mut Lapin| $callback) as Callback
+        Box::new(|_: &mut Lapin| {
+            $callback;
+        }) as Callback
     };
     ($name:ident, $callback:expr) => {
-        Box::new(|$name: &mut Lapin| $callback) as Callback
+        Box::new(|$name: &mut Lapin| {
+            $callback;
+        }) as Callback
     };
 }
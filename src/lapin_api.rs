@@ -1,14 +1,18 @@
 //! This module defines a bunch of useful public functions to the `Lapin`
 //! struct. Check then on docs for `Lapin`.
 use crate::config::Config;
+use crate::ipc::{self, IpcServer};
 use crate::keys::{match_mods, Callback, KeybindSet};
+use crate::layouts::{self, LayoutMessage};
 use crate::screens::Screen;
-use crate::{Atoms, Lapin};
+use crate::spawn::{self, Spawn};
+use crate::{Atoms, Lapin, Retile, SpawnRecord};
+use std::collections::HashMap;
 use std::process;
+use std::time;
 use xcb::x;
 use xcb::xinerama;
 use xcb::Connection;
-use xcb::Xid;
 
 impl Lapin {
     /// The first function that should be called: to connect the window manager
@@ -35,6 +39,20 @@ impl Lapin {
             keybinds,
             root,
             atoms,
+            sticky: Vec::new(),
+            hidden: Vec::new(),
+            urgent: Vec::new(),
+            maximized: HashMap::new(),
+            fullscreen: HashMap::new(),
+            closing: HashMap::new(),
+            spawn_records: Vec::new(),
+            ipc: None,
+            border_widths: HashMap::new(),
+            border_colors: HashMap::new(),
+            late_rules_applied: HashMap::new(),
+            rule_properties: HashMap::new(),
+            docks: Vec::new(),
+            unannounced: None,
         }
     }
 
@@ -72,6 +90,24 @@ impl Lapin {
             modifiers: match_mods(self.config.mouse_mod).0,
         });
 
+        // don't leave zombies behind
+        spawn::setup_reaper();
+        spawn::set_tracking(self.config.spawn_on_origin);
+
+        // listen for commands. it's not fatal if we can't, the window
+        // manager is still usable with keybinds.
+        if self.config.ipc {
+            match IpcServer::bind() {
+                Ok(ipc) => {
+                    // so programs we spawn find us even if the path was
+                    // computed from the environment.
+                    std::env::set_var("LAPIN_SOCKET", ipc::socket_path());
+                    self.ipc = Some(ipc);
+                }
+                Err(e) => eprintln!("Cannot listen for commands: {e}"),
+            }
+        }
+
         // register events
         let event_mask = x::EventMask::SUBSTRUCTURE_NOTIFY
             | x::EventMask::STRUCTURE_NOTIFY
@@ -83,6 +119,10 @@ impl Lapin {
             value_list: &[x::Cw::EventMask(event_mask)],
         });
 
+        // layouts that can't be copied are shared by the workspaces.
+        let layouts = std::mem::take(&mut self.config.layouts);
+        self.config.layouts = layouts::make_shareable(layouts);
+
         // setup monitors
         let cookie = self.x_connection.send_request(&xinerama::QueryScreens {});
         let reply = self
@@ -98,6 +138,8 @@ impl Lapin {
                 screen.y_org,
             ));
         }
+        self.name_screens();
+        self.apply_screen_configs();
 
         self.x_connection.send_request(&x::SetInputFocus {
             revert_to: x::InputFocus::PointerRoot,
@@ -207,14 +249,30 @@ impl Lapin {
                 self.atoms.net_wm_desktop,
                 self.atoms.net_wm_state,
                 self.atoms.net_wm_state_fullscreen,
+                self.atoms.net_wm_state_maximized_vert,
+                self.atoms.net_wm_state_maximized_horz,
+                self.atoms.net_wm_state_above,
+                self.atoms.net_wm_state_below,
+                self.atoms.net_wm_state_sticky,
+                self.atoms.net_wm_state_hidden,
+                self.atoms.net_wm_state_demands_attention,
                 self.atoms.net_wm_action_fullscreen,
+                self.atoms.net_workarea,
+                self.atoms.net_wm_strut,
+                self.atoms.net_wm_strut_partial,
             ],
         });
 
+        // manage windows mapped before we started
+        self.adopt_windows();
+        // publish the work area even if there's no dock.
+        self.update_struts();
+
         // if has a callback, calls it
         if let Some(callback) = callback {
             callback(self);
         }
+        self.track_spawned();
 
         // current desktop is set after the callback so the user can
         // change it without pain if wishes.
@@ -231,6 +289,11 @@ impl Lapin {
         self.main_event_loop(keybinds);
     }
 
+    /// Returns the index of the currently focused screen.
+    pub fn get_current_screen(&self) -> usize {
+        self.current_scr
+    }
+
     /// Returns the id of the currently focused window.
     pub fn get_focused_window(&self) -> Option<x::Window> {
         if let Some(w) = self.current_workspace().focused {
@@ -244,13 +307,25 @@ impl Lapin {
         }
     }
 
-    /// Kills the currently focused client.
+    /// Kills the currently focused client, without asking. Note that it
+    /// kills the entire client, so all windows of a program with many of
+    /// them will die. Prefer `close_focused()`.
     pub fn killfocused(&mut self) {
-        let Some(window) = self.get_focused_window() else { return };
-        self.x_connection.send_request(&x::KillClient {
-            resource: window.resource_id(),
-        });
-        self.x_connection.flush().ok();
+        let Some(window) = self.get_focused_window() else {
+            return;
+        };
+        self.kill_window(window);
+    }
+
+    /// Closes the currently focused window gracefully. The window is asked
+    /// to close itself (WM_DELETE_WINDOW), so the program may, e.g., ask to
+    /// save changes. The client is killed if it doesn't support being
+    /// asked, if the window was already asked before, or after
+    /// `Config::close_timeout`.
+    pub fn close_focused(&mut self) {
+        if let Some(window) = self.get_focused_window() {
+            self.close_window(window);
+        }
     }
 
     /// Changes the focus to the next window of the current workspace.
@@ -268,6 +343,54 @@ impl Lapin {
         self.change_layout(false);
     }
 
+    /// Sends a message to the layout of the current workspace, e.g., to grow
+    /// the master area, and re-tiles the workspace. Only the layout of the
+    /// current workspace changes, not the same layout in the others. Layouts
+    /// ignore messages they don't understand.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use le_petit_lapin::keys::*;
+    /// use le_petit_lapin::layouts::*;
+    /// use le_petit_lapin::*;
+    /// let mut keybinds = KeybindSet::new();
+    /// keybinds.bindall(vec![
+    ///     (&["Super"], "l", lazy! {wm, wm.send_layout_message(LayoutMessage::IncMasterFactor(0.05))}),
+    ///     (&["Super"], "h", lazy! {wm, wm.send_layout_message(LayoutMessage::DecMasterFactor(0.05))}),
+    /// ]);
+    /// ```
+    pub fn send_layout_message(&mut self, message: LayoutMessage) {
+        let border = self.current_layout().border_width();
+        let workspace = self.current_workspace_mut();
+        let l = workspace.layout;
+        workspace.layouts[l].handle_message(&message);
+        let new_border = self.current_layout().border_width();
+        if new_border != border {
+            for window in &self.current_workspace().windows {
+                self.x_connection.send_request(&x::ConfigureWindow {
+                    window: *window,
+                    value_list: &[x::ConfigWindow::BorderWidth(
+                        self.border_width_for(*window, new_border as u32),
+                    )],
+                });
+            }
+        }
+        self.retile_current(Retile::Reload);
+        self.x_connection.flush().ok();
+    }
+
+    /// Puts one more window in the master area of the current workspace, if
+    /// its layout has one.
+    pub fn inc_master(&mut self) {
+        self.send_layout_message(LayoutMessage::IncMaster);
+    }
+
+    /// Puts one less window in the master area of the current workspace, if
+    /// its layout has one.
+    pub fn dec_master(&mut self) {
+        self.send_layout_message(LayoutMessage::DecMaster);
+    }
+
     /// Changes to the previous layout of the current workspace.
     pub fn prev_layout(&mut self) {
         self.change_layout(true);
@@ -278,6 +401,7 @@ impl Lapin {
         if self.current_screen().current_wk == wk {
             return;
         }
+        self.move_sticky_windows(self.current_screen().current_wk, wk);
         for window in &self.current_workspace().windows {
             self.x_connection
                 .send_request(&x::UnmapWindow { window: *window });
@@ -297,12 +421,16 @@ impl Lapin {
             data: &[self.current_screen().current_wk as u32],
         });
         for window in &self.current_workspace().windows {
-            self.x_connection
-                .send_request(&x::MapWindow { window: *window });
+            if !self.hidden.contains(window) {
+                self.x_connection
+                    .send_request(&x::MapWindow { window: *window });
+            }
         }
         for window in &self.current_workspace().ool_windows {
-            self.x_connection
-                .send_request(&x::MapWindow { window: *window });
+            if !self.hidden.contains(window) {
+                self.x_connection
+                    .send_request(&x::MapWindow { window: *window });
+            }
         }
         self.x_connection.flush().ok();
         if let Some(focus) = self.current_workspace().focused {
@@ -323,16 +451,10 @@ impl Lapin {
                 time: x::CURRENT_TIME,
             });
         }
-        let (width, height, x, y) = self.calculate_layout_coordinates();
-        self.current_layout().reload(
-            &mut self.workspace_windows(),
-            &self.x_connection,
-            width,
-            height,
-            x,
-            y,
-        );
+        self.retile_current(Retile::Reload);
         self.x_connection.flush().ok();
+        self.emit_workspace(self.current_scr);
+        self.emit_focus();
     }
 
     /// Rotate the current workspace stack up.
@@ -342,15 +464,7 @@ impl Lapin {
         }
         if let Some(cur_w) = self.current_workspace().focused {
             self.current_workspace_mut().windows.rotate_left(1);
-            let (width, height, x, y) = self.calculate_layout_coordinates();
-            self.current_layout().reload(
-                &mut self.workspace_windows(),
-                &self.x_connection,
-                width,
-                height,
-                x,
-                y,
-            );
+            self.retile_current(Retile::Reload);
             self.current_workspace_mut().focused = if cur_w == 0 {
                 Some(self.current_workspace().windows.len() - 1)
             } else {
@@ -366,15 +480,7 @@ impl Lapin {
         }
         if let Some(cur_w) = self.current_workspace().focused {
             self.current_workspace_mut().windows.rotate_right(1);
-            let (width, height, x, y) = self.calculate_layout_coordinates();
-            self.current_layout().reload(
-                &mut self.workspace_windows(),
-                &self.x_connection,
-                width,
-                height,
-                x,
-                y,
-            );
+            self.retile_current(Retile::Reload);
             self.current_workspace_mut().focused =
                 if cur_w == self.current_workspace().windows.len() - 1 {
                     Some(0)
@@ -406,15 +512,7 @@ impl Lapin {
 
             self.current_workspace_mut().focused = Some(next_w);
 
-            let (width, height, x, y) = self.calculate_layout_coordinates();
-            self.current_layout().reload(
-                &mut self.workspace_windows(),
-                &self.x_connection,
-                width,
-                height,
-                x,
-                y,
-            );
+            self.retile_current(Retile::Reload);
         }
     }
 
@@ -440,15 +538,7 @@ impl Lapin {
 
             self.current_workspace_mut().focused = Some(prev_w);
 
-            let (width, height, x, y) = self.calculate_layout_coordinates();
-            self.current_layout().reload(
-                &mut self.workspace_windows(),
-                &self.x_connection,
-                width,
-                height,
-                x,
-                y,
-            );
+            self.retile_current(Retile::Reload);
         }
     }
 
@@ -470,15 +560,7 @@ impl Lapin {
             self.current_workspace_mut().windows[other_w] = tmp;
             self.current_workspace_mut().focused = Some(other_w);
 
-            let (width, height, x, y) = self.calculate_layout_coordinates();
-            self.current_layout().reload(
-                &mut self.workspace_windows(),
-                &self.x_connection,
-                width,
-                height,
-                x,
-                y,
-            );
+            self.retile_current(Retile::Reload);
         }
     }
 
@@ -486,15 +568,7 @@ impl Lapin {
     pub fn toggle_reserved_space(&mut self) {
         self.current_workspace_mut().respect_reserved_space =
             !self.current_workspace().respect_reserved_space;
-        let (width, height, x, y) = self.calculate_layout_coordinates();
-        self.current_layout().reload(
-            &mut self.workspace_windows(),
-            &self.x_connection,
-            width,
-            height,
-            x,
-            y,
-        );
+        self.retile_current(Retile::Reload);
         self.x_connection.flush().ok();
     }
 
@@ -505,53 +579,35 @@ impl Lapin {
         if let Some(w) = self.current_workspace().focused {
             if self.current_workspace().ool_focus {
                 let window = self.current_workspace_mut().ool_windows.remove(w);
+                // a fullscreen window sent to the layout isn't fullscreen
+                // anymore.
+                if self.fullscreen.remove(&window).is_some() {
+                    self.set_net_wm_state(window, self.atoms.net_wm_state_fullscreen, false);
+                }
                 self.current_workspace_mut().windows.insert(0, window);
                 self.current_workspace_mut().ool_focus = false;
                 self.current_workspace_mut().focused = Some(0);
-                let (width, height, x, y) = self.calculate_layout_coordinates();
-                self.current_layout().newwin(
-                    &mut self.workspace_windows(),
-                    &self.x_connection,
-                    width,
-                    height,
-                    x,
-                    y,
-                );
+                self.retile_current(Retile::NewWindow);
                 self.x_connection.send_request(&x::ConfigureWindow {
                     window,
-                    value_list: &[x::ConfigWindow::BorderWidth(
+                    value_list: &[x::ConfigWindow::BorderWidth(self.border_width_for(
+                        window,
                         self.current_layout().border_width() as u32,
-                    )],
+                    ))],
                 });
             } else {
                 let window = self.current_workspace_mut().windows.remove(w);
                 self.current_workspace_mut().ool_windows.insert(0, window);
                 self.current_workspace_mut().ool_focus = true;
                 self.current_workspace_mut().focused = Some(0);
-                let (width, height, x, y) = self.calculate_layout_coordinates();
-                self.current_layout().delwin(
-                    &mut self.workspace_windows(),
-                    self.current_workspace().focused,
-                    &self.x_connection,
-                    width,
-                    height,
-                    x,
-                    y,
-                );
+                self.retile_current(Retile::DeletedWindow(self.current_workspace().focused));
                 self.x_connection.send_request(&x::ConfigureWindow {
                     window,
                     value_list: &[x::ConfigWindow::BorderWidth(
-                        self.config.border_width as u32,
+                        self.border_width_for(window, self.config.border_width),
                     )],
                 });
-                self.x_connection
-                    .send_request(&x::ChangeProperty::<x::Atom> {
-                        mode: x::PropMode::Replace,
-                        window,
-                        property: self.atoms.net_wm_state,
-                        r#type: x::ATOM_ATOM,
-                        data: &[],
-                    });
+                self.set_net_wm_state(window, self.atoms.net_wm_state_fullscreen, false);
             }
             self.x_connection.flush().ok();
         }
@@ -612,16 +668,7 @@ impl Lapin {
             self.x_connection.flush().ok();
 
             if !ool {
-                let (width, height, x, y) = self.calculate_layout_coordinates();
-                self.current_layout().delwin(
-                    &mut self.workspace_windows(),
-                    self.current_workspace().focused,
-                    &self.x_connection,
-                    width,
-                    height,
-                    x,
-                    y,
-                );
+                self.retile_current(Retile::DeletedWindow(self.current_workspace().focused));
             }
             self.x_connection.flush().ok();
         }
@@ -641,49 +688,67 @@ impl Lapin {
         }
     }
 
-    /// Fullscreens a window. Kind of a hack, just toggles ool, sets x and y to the monitor
-    /// location and removes the border.
+    /// Fullscreens the focused window. The window is taken out of the
+    /// layout until leaving fullscreen with `toggle_fullscreen()`.
     pub fn fullscreen(&mut self) {
         if let Some(window) = self.get_focused_window() {
-            if !self.current_workspace().ool_focus {
-                self.toggle_ool();
-            }
-            let list = [
-                x::ConfigWindow::X(self.current_screen().x as i32),
-                x::ConfigWindow::Y(self.current_screen().y as i32),
-                x::ConfigWindow::Width(self.current_screen().width as u32),
-                x::ConfigWindow::Height(self.current_screen().height as u32),
-                x::ConfigWindow::BorderWidth(0),
-                x::ConfigWindow::StackMode(x::StackMode::Above),
-            ];
-            self.x_connection.send_request(&x::ConfigureWindow {
-                window,
-                value_list: &list,
-            });
-            self.x_connection.send_request(&x::ChangeProperty {
-                mode: x::PropMode::Replace,
-                window: window,
-                property: self.atoms.net_wm_state,
-                r#type: x::ATOM_ATOM,
-                data: &[self.atoms.net_wm_state_fullscreen],
-            });
-            self.x_connection.flush().ok();
+            self.set_fullscreen(window, true);
         }
     }
 
-    /// Runs a system command. Arguments must be separated by spaces.
-    /// Note that it DOES NOT runs it inside a shell.
-    pub fn spawn(s: &str) {
-        let mut iter = s.split_whitespace();
-        if let Some(prog) = iter.next() {
-            let mut cmd = process::Command::new(prog);
-            for arg in iter {
-                cmd.arg(arg);
-            }
-            cmd.spawn().ok();
+    /// Toggles fullscreen of the focused window. When leaving fullscreen,
+    /// the window goes back to where it was: its position in the layout or
+    /// its floating geometry and border.
+    pub fn toggle_fullscreen(&mut self) {
+        if let Some(window) = self.get_focused_window() {
+            let enable = !self.fullscreen.contains_key(&window);
+            self.set_fullscreen(window, enable);
         }
     }
 
+    /// Runs a system command. Arguments must be separated by spaces, and
+    /// may be quoted as in a shell. Note that it DOES NOT runs it inside a
+    /// shell, use `Lapin::spawn_shell()` for that. Returns the PID of the
+    /// program, if it could be spawned.
+    ///
+    /// Check `spawn::Spawn` to change the environment and working
+    /// directory of the program.
+    pub fn spawn(s: &str) -> Option<u32> {
+        Spawn::new(s).spawn()
+    }
+
+    /// Runs a command inside `sh`, so shell syntax like pipes and
+    /// redirections can be used. Returns the PID of the shell.
+    pub fn spawn_shell(s: &str) -> Option<u32> {
+        Spawn::shell(s).spawn()
+    }
+
+    /// Runs a program with a list of arguments, the first being the
+    /// program itself. Returns the PID of the program.
+    pub fn spawn_argv(argv: &[&str]) -> Option<u32> {
+        Spawn::argv(argv).spawn()
+    }
+
+    /// Runs a system command, like `Lapin::spawn()`, but places its windows
+    /// in a workspace of the current screen, even if they take a while to
+    /// show up. Returns the PID of the program.
+    pub fn spawn_on(&mut self, workspace: usize, s: &str) -> Option<u32> {
+        self.spawn_with_on(workspace, &Spawn::new(s))
+    }
+
+    /// Same as `Lapin::spawn_on()`, but with a `spawn::Spawn`.
+    pub fn spawn_with_on(&mut self, workspace: usize, spawn: &Spawn) -> Option<u32> {
+        let (pid, startup_id) = spawn.spawn_with_startup_id()?;
+        self.spawn_records.push(SpawnRecord {
+            pid,
+            startup_id,
+            screen: self.current_scr,
+            workspace,
+            time: time::Instant::now(),
+        });
+        Some(pid)
+    }
+
     /// Terminate the window manager process.
     pub fn quit() {
         process::exit(0);
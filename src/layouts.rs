@@ -1,16 +1,89 @@
 //! Default layouts for the window manager and a trait to create new
 //! ones.
 
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::slice::Iter;
 use xcb::x;
 use xcb::Connection;
 
-/// A trait that defines a layout of the window manager. Layouts are
-/// responsible to send requests to change windows size and position. They're
-/// free to do anything, but to better suit with the window manager itself,
-/// they should stick to just changing windows size and position.
-pub trait Layout {
-    /// Called when a window is mapped, except when changing workspaces.
+/// A rectangle in the screen. When it's the geometry of a window, the size
+/// doesn't include the border.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// A message sent to the layout of the current workspace with
+/// `Lapin::send_layout_message()`, to change its settings at runtime.
+/// Layouts ignore the messages they don't understand.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayoutMessage {
+    /// Grows the master area by a fraction of the screen.
+    IncMasterFactor(f32),
+    /// Shrinks the master area by a fraction of the screen.
+    DecMasterFactor(f32),
+    /// Puts one more window in the master area.
+    IncMaster,
+    /// Puts one less window in the master area.
+    DecMaster,
+    /// Widens the gaps by some pixels.
+    IncGaps(u16),
+    /// Narrows the gaps by some pixels.
+    DecGaps(u16),
+    /// Widens the borders by some pixels.
+    IncBorders(u16),
+    /// Narrows the borders by some pixels.
+    DecBorders(u16),
+    /// A message for custom layouts.
+    Custom(&'static str),
+}
+
+/// Returns a master factor changed by some amount, keeping both areas
+/// visible.
+fn change_master_factor(master_factor: f32, amount: f32) -> f32 {
+    (master_factor + amount).clamp(0.05, 0.95)
+}
+
+/// Changes gaps or borders as asked by a message, if it's about them.
+fn change_size(size: &mut u16, message: &LayoutMessage, gaps: bool) {
+    *size = match (message, gaps) {
+        (LayoutMessage::IncGaps(n), true) | (LayoutMessage::IncBorders(n), false) => {
+            size.saturating_add(*n)
+        }
+        (LayoutMessage::DecGaps(n), true) | (LayoutMessage::DecBorders(n), false) => {
+            size.saturating_sub(*n)
+        }
+        _ => *size,
+    };
+}
+
+/// A layout that can't be copied, shared by every workspace that uses it.
+struct SharedLayout(Rc<RefCell<Box<dyn Layout>>>);
+
+impl Layout for SharedLayout {
+    fn arrange(
+        &self,
+        area: Rect,
+        windows: &[x::Window],
+        focused: Option<usize>,
+    ) -> Vec<(x::Window, Rect)> {
+        self.0.borrow().arrange(area, windows, focused)
+    }
     fn newwin(
         &self,
         windows: &mut Iter<x::Window>,
@@ -19,8 +92,9 @@ pub trait Layout {
         height: u16,
         x: i16,
         y: i16,
-    );
-    /// Called when a window is unmaped, except when changing workspaces.
+    ) {
+        self.0.borrow().newwin(windows, con, width, height, x, y)
+    }
     fn delwin(
         &self,
         windows: &mut Iter<x::Window>,
@@ -30,9 +104,11 @@ pub trait Layout {
         height: u16,
         x: i16,
         y: i16,
-    );
-    /// Called any time some action requires a full reload of the windows size
-    /// and/or position, such as changing workspaces or layouts.
+    ) {
+        self.0
+            .borrow()
+            .delwin(windows, current, con, width, height, x, y)
+    }
     fn reload(
         &self,
         windows: &mut Iter<x::Window>,
@@ -41,8 +117,9 @@ pub trait Layout {
         height: u16,
         x: i16,
         y: i16,
-    );
-    /// Called when the focus was changed.
+    ) {
+        self.0.borrow().reload(windows, con, width, height, x, y)
+    }
     fn changewin(
         &self,
         windows: &mut Iter<x::Window>,
@@ -52,41 +129,83 @@ pub trait Layout {
         height: u16,
         x: i16,
         y: i16,
-    );
-    /// The window manager calls this function when a mouse motion is
-    /// performed to check if it should allow it to move and/or resize windows.
-    /// Layouts will generally just return `false` unless they're a floating
-    /// layout.
-    fn allow_motions(&self) -> bool;
-    /// The window manager calls this function to get the border size it should
-    /// set to windows. Layouts should return 0 to no border.
-    fn border_width(&self) -> u16;
-
-    /// Returns the layout name. It's recommended to leave the name as a free
-    /// choice of the user.
-    fn name(&self) -> &'static str;
+    ) {
+        self.0
+            .borrow()
+            .changewin(windows, number, con, width, height, x, y)
+    }
+    fn allow_motions(&self) -> bool {
+        self.0.borrow().allow_motions()
+    }
+    fn border_width(&self) -> u16 {
+        self.0.borrow().border_width()
+    }
+    fn size_hints(&self) -> bool {
+        self.0.borrow().size_hints()
+    }
+    fn handle_message(&mut self, message: &LayoutMessage) {
+        self.0.borrow_mut().handle_message(message)
+    }
+    fn boxed_clone(&self) -> Option<Box<dyn Layout>> {
+        Some(Box::new(SharedLayout(Rc::clone(&self.0))))
+    }
+    fn name(&self) -> &'static str {
+        self.0.borrow().name()
+    }
 }
 
-/// A floating layout. Does nothing with the windows and allows motions.
-/// Supports optional borders.
-pub struct Floating {
-    pub borders: u16,
-    pub name: &'static str,
+/// Wraps the layouts that can't be copied so every workspace shares them.
+/// Called by `Lapin::init()`, so `boxed_clone()` works for every layout in
+/// `Config::layouts` afterwards.
+pub(crate) fn make_shareable(layouts: Vec<Box<dyn Layout>>) -> Vec<Box<dyn Layout>> {
+    layouts
+        .into_iter()
+        .map(|layout| match layout.boxed_clone() {
+            Some(_) => layout,
+            None => Box::new(SharedLayout(Rc::new(RefCell::new(layout)))) as Box<dyn Layout>,
+        })
+        .collect()
 }
 
-impl Floating {
-    /// Returns a new floating layout with default configs:
-    /// - 4 pixels for borders;
-    /// - "Floating" as the name.
-    pub fn new() -> Self {
-        Floating {
-            borders: 4,
-            name: "Floating",
-        }
+/// A trait that defines a layout of the window manager. Layouts compute
+/// where the windows go with `arrange()`, and the window manager moves them
+/// there.
+///
+/// Every workspace has its own copy of each layout, made from
+/// `Config::layouts` with `boxed_clone()`, so changing one with
+/// `handle_message()` doesn't change the others. Layouts that don't
+/// implement `boxed_clone()` are shared by every workspace instead, as
+/// with older versions.
+///
+/// Layouts written before `arrange()` existed send requests to the X server
+/// themselves in the other methods, that are still called after
+/// `arrange()`. They should move to `arrange()`, as the other methods will
+/// eventually go away.
+pub trait Layout {
+    /// Returns the geometry of the windows, given the area of the screen
+    /// the layout may use and the index of the focused window, if it's one
+    /// of them. Windows left out of the result aren't touched. Defaults to
+    /// leaving every window where it is.
+    ///
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::layouts::*;
+    /// use xcb::{x, XidNew};
+    /// let windows: Vec<x::Window> = (1..=3).map(|id| unsafe { x::Window::new(id) }).collect();
+    /// let area = Rect::new(0, 0, 1000, 600);
+    /// let arrangement = Maximized::new().arrange(area, &windows, Some(0));
+    /// assert!(arrangement.iter().all(|(_, rect)| *rect == area));
+    /// ```
+    fn arrange(
+        &self,
+        _area: Rect,
+        _windows: &[x::Window],
+        _focused: Option<usize>,
+    ) -> Vec<(x::Window, Rect)> {
+        Vec::new()
     }
-}
-
-impl Layout for Floating {
+    /// Called when a window is mapped, except when changing workspaces.
+    /// Only for layouts that don't implement `arrange()`.
     fn newwin(
         &self,
         _windows: &mut Iter<x::Window>,
@@ -97,6 +216,8 @@ impl Layout for Floating {
         _y: i16,
     ) {
     }
+    /// Called when a window is unmaped, except when changing workspaces.
+    /// Only for layouts that don't implement `arrange()`.
     fn delwin(
         &self,
         _windows: &mut Iter<x::Window>,
@@ -108,6 +229,9 @@ impl Layout for Floating {
         _y: i16,
     ) {
     }
+    /// Called any time some action requires a full reload of the windows size
+    /// and/or position, such as changing workspaces or layouts. Only for
+    /// layouts that don't implement `arrange()`.
     fn reload(
         &self,
         _windows: &mut Iter<x::Window>,
@@ -118,6 +242,8 @@ impl Layout for Floating {
         _y: i16,
     ) {
     }
+    /// Called when the focus was changed. Only for layouts that don't
+    /// implement `arrange()`.
     fn changewin(
         &self,
         _windows: &mut Iter<x::Window>,
@@ -129,44 +255,157 @@ impl Layout for Floating {
         _y: i16,
     ) {
     }
+    /// The window manager calls this function when a mouse motion is
+    /// performed to check if it should allow it to move and/or resize windows.
+    /// Layouts will generally just return `false` unless they're a floating
+    /// layout.
+    fn allow_motions(&self) -> bool;
+    /// The window manager calls this function to get the border size it should
+    /// set to windows. Layouts should return 0 to no border.
+    fn border_width(&self) -> u16;
+    /// If the window manager should shrink windows to follow their size
+    /// hints, centering them in the geometry returned by `arrange()`.
+    /// Defaults to `false`.
+    fn size_hints(&self) -> bool {
+        false
+    }
+    /// Changes the layout settings as a message asks. The window manager
+    /// re-tiles the workspace afterwards. Defaults to ignoring every
+    /// message.
+    ///
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::layouts::*;
+    /// let mut tiling = Tiling::new();
+    /// tiling.handle_message(&LayoutMessage::IncMasterFactor(0.1));
+    /// tiling.handle_message(&LayoutMessage::DecGaps(10));
+    /// tiling.handle_message(&LayoutMessage::Custom("unknown"));
+    /// assert_eq!(tiling.master_factor, 0.6);
+    /// assert_eq!(tiling.gaps, 0);
+    /// ```
+    fn handle_message(&mut self, _message: &LayoutMessage) {}
+    /// Returns a copy of the layout for a workspace, so each workspace may
+    /// change its own with `handle_message()`. Layouts that implement
+    /// `Clone` can just return `Some(Box::new(self.clone()))`. Defaults to
+    /// `None`, sharing the layout between every workspace.
+    fn boxed_clone(&self) -> Option<Box<dyn Layout>> {
+        None
+    }
+
+    /// Returns the layout name. It's recommended to leave the name as a free
+    /// choice of the user.
+    fn name(&self) -> &'static str;
+}
+
+/// Returns the geometry of a window that fills a cell, as the border is
+/// drawn outside of it. Never returns an empty size.
+fn fill_cell(x: i64, y: i64, width: i64, height: i64, borders: i64) -> Rect {
+    Rect {
+        x: x as i32,
+        y: y as i32,
+        width: (width - borders * 2).max(1) as u32,
+        height: (height - borders * 2).max(1) as u32,
+    }
+}
+
+/// Splits a length in `n` parts with gaps around and between them, as
+/// (start, length) pairs. The last part takes what's left of the division.
+fn split(start: i64, length: i64, n: usize, gaps: i64) -> Vec<(i64, i64)> {
+    let n = n as i64;
+    let available = length - gaps * (n + 1);
+    let each = (available / n).max(1);
+    (0..n)
+        .map(|i| {
+            let part = if i == n - 1 {
+                (available - each * (n - 1)).max(1)
+            } else {
+                each
+            };
+            (start + gaps + i * (each + gaps), part)
+        })
+        .collect()
+}
+
+/// A floating layout. Does nothing with the windows and allows motions.
+/// Supports optional borders.
+#[derive(Clone)]
+pub struct Floating {
+    pub borders: u16,
+    pub name: &'static str,
+}
+
+impl Floating {
+    /// Returns a new floating layout with default configs:
+    /// - 4 pixels for borders;
+    /// - "Floating" as the name.
+    pub fn new() -> Self {
+        Floating {
+            borders: 4,
+            name: "Floating",
+        }
+    }
+}
+
+impl Layout for Floating {
     fn allow_motions(&self) -> bool {
         true
     }
     fn border_width(&self) -> u16 {
         self.borders
     }
+    fn handle_message(&mut self, message: &LayoutMessage) {
+        change_size(&mut self.borders, message, false);
+    }
+    fn boxed_clone(&self) -> Option<Box<dyn Layout>> {
+        Some(Box::new(self.clone()))
+    }
     fn name(&self) -> &'static str {
         self.name
     }
 }
 
 /// A tiling layout, similar to DWM. Supports optional gaps and borders.
+#[derive(Clone)]
 pub struct Tiling {
     pub name: &'static str,
     pub borders: u16,
-    /// Ratio of the screen used by the master window. Ranges from 0 to 1.
+    /// Ratio of the screen used by the master windows. Ranges from 0 to 1.
     pub master_factor: f32,
+    /// How many windows go in the master area. With 0, every window is
+    /// stacked.
+    pub nmaster: usize,
     /// Gaps around and between the windows.
     pub gaps: u16,
+    /// If windows should be sized following their size hints, e.g., to
+    /// a whole number of cells for terminals, being centered in the space
+    /// they'd fill.
+    pub size_hints: bool,
 }
 
 impl Tiling {
     /// Creates a new tiling layout with default configs:
     /// - 4 pixels for borders;
     /// - 1/2 (0.5) of master factor;
+    /// - 1 master window;
     /// - 4 pixels for gaps;
+    /// - size hints ignored;
     /// - "Tiling" as the name.
     pub fn new() -> Tiling {
         Tiling {
             name: "Tiling",
             borders: 4,
             master_factor: 1.0 / 2.0,
+            nmaster: 1,
             gaps: 4,
+            size_hints: false,
         }
     }
 }
 
 impl Layout for Tiling {
+    fn boxed_clone(&self) -> Option<Box<dyn Layout>> {
+        Some(Box::new(self.clone()))
+    }
     fn name(&self) -> &'static str {
         self.name
     }
@@ -176,133 +415,134 @@ impl Layout for Tiling {
     fn border_width(&self) -> u16 {
         self.borders
     }
-
-    fn reload(
-        &self,
-        windows: &mut Iter<x::Window>,
-        con: &Connection,
-        width: u16,
-        height: u16,
-        x: i16,
-        y: i16,
-    ) {
-        let n_wins = windows.len();
-        if n_wins == 0 {
-            return;
-        } else if n_wins == 1 {
-            let list = [
-                x::ConfigWindow::X((x + (self.gaps as i16)) as i32),
-                x::ConfigWindow::Y((y + (self.gaps as i16)) as i32),
-                x::ConfigWindow::Width(
-                    (width - ((self.gaps * 2) as u16) - ((self.borders * 2) as u16)) as u32,
-                ),
-                x::ConfigWindow::Height(
-                    (height - ((self.gaps * 2) as u16) - ((self.borders * 2) as u16)) as u32,
-                ),
-            ];
-            con.send_request(&x::ConfigureWindow {
-                window: *windows.next().unwrap(),
-                value_list: &list,
-            });
-        } else {
-            let list = [
-                x::ConfigWindow::X((x + (self.gaps as i16)) as i32),
-                x::ConfigWindow::Y((y + (self.gaps as i16)) as i32),
-                x::ConfigWindow::Width(
-                    ((((width as f32) * self.master_factor) as u16)
-                        - (((self.gaps as f32) * 1.5) as u16)
-                        - (self.borders * 2)) as u32,
-                ),
-                x::ConfigWindow::Height((height - (self.gaps * 2) - (self.borders * 2)) as u32),
-            ];
-            con.send_request(&x::ConfigureWindow {
-                window: *windows.next().unwrap(),
-                value_list: &list,
-            });
-            let n_slave_wins = n_wins - 1;
-            let x = x + (((((width as f32) * self.master_factor) as u16) + (self.gaps / 2)) as i16);
-            let width = (width / 2) - (((self.gaps as f32) * 1.5) as u16) - (self.borders * 2);
-            let height = (height
-                - (self.gaps * (n_slave_wins + 1) as u16)
-                - (self.borders * 2 * (n_slave_wins as u16)))
-                / (n_slave_wins as u16);
-            for (n, window) in windows.enumerate() {
-                let y = y
-                    + (((height * (n as u16) + (self.borders * 2 * (n as u16)))
-                        + (self.gaps * ((n + 1) as u16))) as i16);
-                let list = [
-                    x::ConfigWindow::X(x as i32),
-                    x::ConfigWindow::Y(y as i32),
-                    x::ConfigWindow::Width(width as u32),
-                    x::ConfigWindow::Height(height as u32),
-                ];
-                con.send_request(&x::ConfigureWindow {
-                    window: *window,
-                    value_list: &list,
-                });
+    fn size_hints(&self) -> bool {
+        self.size_hints
+    }
+    fn handle_message(&mut self, message: &LayoutMessage) {
+        match message {
+            LayoutMessage::IncMasterFactor(amount) => {
+                self.master_factor = change_master_factor(self.master_factor, *amount)
+            }
+            LayoutMessage::DecMasterFactor(amount) => {
+                self.master_factor = change_master_factor(self.master_factor, -amount)
+            }
+            LayoutMessage::IncMaster => self.nmaster = self.nmaster.saturating_add(1),
+            LayoutMessage::DecMaster => self.nmaster = self.nmaster.saturating_sub(1),
+            _ => {
+                change_size(&mut self.gaps, message, true);
+                change_size(&mut self.borders, message, false);
             }
         }
-        con.flush().ok();
     }
 
-    fn newwin(
-        &self,
-        windows: &mut Iter<x::Window>,
-        con: &Connection,
-        width: u16,
-        height: u16,
-        x: i16,
-        y: i16,
-    ) {
-        self.reload(windows, con, width, height, x, y);
-    }
-    fn delwin(
-        &self,
-        windows: &mut Iter<x::Window>,
-        _current: Option<usize>,
-        con: &Connection,
-        width: u16,
-        height: u16,
-        x: i16,
-        y: i16,
-    ) {
-        self.reload(windows, con, width, height, x, y);
-    }
-    fn changewin(
+    /// The first `nmaster` windows are stacked on the left, and the others
+    /// on the right. If every window is a master, or none is, they're all
+    /// stacked in a single column.
+    ///
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::layouts::*;
+    /// use xcb::{x, XidNew};
+    /// let windows: Vec<x::Window> = (1..=3).map(|id| unsafe { x::Window::new(id) }).collect();
+    /// let mut tiling = Tiling::new();
+    /// tiling.gaps = 0;
+    /// tiling.borders = 0;
+    /// let arrangement = tiling.arrange(Rect::new(0, 0, 1000, 600), &windows, None);
+    /// assert_eq!(arrangement[0].1, Rect::new(0, 0, 500, 600));
+    /// assert_eq!(arrangement[1].1, Rect::new(500, 0, 500, 300));
+    /// assert_eq!(arrangement[2].1, Rect::new(500, 300, 500, 300));
+    ///
+    /// // two masters side by side with the stack.
+    /// tiling.nmaster = 2;
+    /// let arrangement = tiling.arrange(Rect::new(0, 0, 1000, 600), &windows, None);
+    /// assert_eq!(arrangement[1].1, Rect::new(0, 300, 500, 300));
+    /// assert_eq!(arrangement[2].1, Rect::new(500, 0, 500, 600));
+    ///
+    /// // no master at all.
+    /// tiling.nmaster = 0;
+    /// let arrangement = tiling.arrange(Rect::new(0, 0, 1000, 600), &windows, None);
+    /// assert_eq!(arrangement[0].1, Rect::new(0, 0, 1000, 200));
+    ///
+    /// // never underflows, even if there's no room for the windows.
+    /// tiling.gaps = 40;
+    /// let arrangement = tiling.arrange(Rect::new(0, 0, 50, 50), &windows, None);
+    /// assert!(arrangement.iter().all(|(_, rect)| rect.width >= 1 && rect.height >= 1));
+    /// ```
+    fn arrange(
         &self,
-        _windows: &mut Iter<x::Window>,
-        _number: usize,
-        _con: &Connection,
-        _width: u16,
-        _height: u16,
-        _x: i16,
-        _y: i16,
-    ) {
+        area: Rect,
+        windows: &[x::Window],
+        _focused: Option<usize>,
+    ) -> Vec<(x::Window, Rect)> {
+        let gaps = self.gaps as i64;
+        let borders = self.borders as i64;
+        let (x, y) = (area.x as i64, area.y as i64);
+        let (width, height) = (area.width as i64, area.height as i64);
+        if windows.is_empty() {
+            return Vec::new();
+        }
+        // a column of windows, from x and with some width.
+        let column = |windows: &[x::Window], column_x: i64, column_width: i64| {
+            let rows = split(y, height, windows.len(), gaps);
+            windows
+                .iter()
+                .zip(rows)
+                .map(|(window, (row_y, row_height))| {
+                    let rect = fill_cell(column_x, row_y, column_width, row_height, borders);
+                    (*window, rect)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let nmaster = self.nmaster.min(windows.len());
+        if nmaster == 0 || nmaster == windows.len() {
+            return column(windows, x + gaps, width - gaps * 2);
+        }
+        let (masters, stack) = windows.split_at(nmaster);
+        let master_width = (width as f32 * self.master_factor.clamp(0.0, 1.0)) as i64;
+        // the gap between the masters and the stack is split between them.
+        let half_gap = gaps / 2;
+        let mut arrangement = column(masters, x + gaps, master_width - gaps - half_gap);
+        arrangement.extend(column(
+            stack,
+            x + master_width + half_gap,
+            width - master_width - gaps - half_gap,
+        ));
+        arrangement
     }
 }
 
 /// A maximized (fullscreen) layout. Windows are drawn above each other.
 /// Supports optional gaps and borders. Use 0 to disable then.
+#[derive(Clone)]
 pub struct Maximized {
     pub name: &'static str,
     pub borders: u16,
     pub gaps: u16,
+    /// If windows should be sized following their size hints, being
+    /// centered in the screen.
+    pub size_hints: bool,
 }
 
 impl Maximized {
     /// Creates a new maximized layout with default configs:
     /// - No borders nor gaps;
+    /// - size hints ignored;
     /// - "Maximized" as the name.
     pub fn new() -> Maximized {
         Maximized {
             name: "Maximized",
             borders: 0,
             gaps: 0,
+            size_hints: false,
         }
     }
 }
 
 impl Layout for Maximized {
+    fn boxed_clone(&self) -> Option<Box<dyn Layout>> {
+        Some(Box::new(self.clone()))
+    }
     fn name(&self) -> &'static str {
         self.name
     }
@@ -315,70 +555,169 @@ impl Layout for Maximized {
         self.borders
     }
 
-    fn newwin(
-        &self,
-        windows: &mut Iter<x::Window>,
-        con: &Connection,
-        width: u16,
-        height: u16,
-        x: i16,
-        y: i16,
-    ) {
-        let window = *windows.next().unwrap();
-        let list = [
-            x::ConfigWindow::X((x + (self.gaps as i16)) as i32),
-            x::ConfigWindow::Y((y + (self.gaps as i16)) as i32),
-            x::ConfigWindow::Width((width - (self.gaps * 2) - (self.borders * 2)) as u32),
-            x::ConfigWindow::Height((height - (self.gaps * 2) - (self.borders * 2)) as u32),
-        ];
-        con.send_request(&x::ConfigureWindow {
-            window,
-            value_list: &list,
-        });
+    fn size_hints(&self) -> bool {
+        self.size_hints
     }
-    fn reload(
+
+    fn handle_message(&mut self, message: &LayoutMessage) {
+        change_size(&mut self.gaps, message, true);
+        change_size(&mut self.borders, message, false);
+    }
+
+    fn arrange(
         &self,
-        windows: &mut Iter<x::Window>,
-        con: &Connection,
-        width: u16,
-        height: u16,
-        x: i16,
-        y: i16,
-    ) {
-        let list = [
-            x::ConfigWindow::X((x + (self.gaps as i16)) as i32),
-            x::ConfigWindow::Y((y + (self.gaps as i16)) as i32),
-            x::ConfigWindow::Width((width - (self.gaps * 2) - (self.borders * 2)) as u32),
-            x::ConfigWindow::Height((height - (self.gaps * 2) - (self.borders * 2)) as u32),
-        ];
-        for window in windows {
-            con.send_request(&x::ConfigureWindow {
-                window: *window,
-                value_list: &list,
-            });
+        area: Rect,
+        windows: &[x::Window],
+        _focused: Option<usize>,
+    ) -> Vec<(x::Window, Rect)> {
+        let gaps = self.gaps as i64;
+        let rect = fill_cell(
+            area.x as i64 + gaps,
+            area.y as i64 + gaps,
+            area.width as i64 - gaps * 2,
+            area.height as i64 - gaps * 2,
+            self.borders as i64,
+        );
+        windows.iter().map(|window| (*window, rect)).collect()
+    }
+}
+
+/// How the `Grid` layout chooses its number of columns.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GridColumns {
+    /// The square root of the number of windows, rounded up.
+    Square,
+    /// A fixed number of columns, or less if there aren't enough windows.
+    Fixed(usize),
+    /// As many columns as needed to make the windows closest to squares,
+    /// given the shape of the screen.
+    Aspect,
+}
+
+/// A grid layout, with windows in rows and columns of the same size. The
+/// windows of the last row share the space of the cells left empty.
+/// Supports optional gaps and borders.
+#[derive(Clone)]
+pub struct Grid {
+    pub name: &'static str,
+    pub borders: u16,
+    /// Gaps around and between the windows.
+    pub gaps: u16,
+    /// How many columns to use.
+    pub columns: GridColumns,
+    /// If windows should be sized following their size hints, being
+    /// centered in their cells.
+    pub size_hints: bool,
+}
+
+impl Grid {
+    /// Creates a new grid layout with default configs:
+    /// - 4 pixels for borders;
+    /// - 4 pixels for gaps;
+    /// - as many columns as the square root of the number of windows;
+    /// - size hints ignored;
+    /// - "Grid" as the name.
+    pub fn new() -> Grid {
+        Grid {
+            name: "Grid",
+            borders: 4,
+            gaps: 4,
+            columns: GridColumns::Square,
+            size_hints: false,
         }
     }
-    fn delwin(
-        &self,
-        _windows: &mut Iter<x::Window>,
-        _current: Option<usize>,
-        _con: &Connection,
-        _width: u16,
-        _height: u16,
-        _x: i16,
-        _y: i16,
-    ) {
+
+    /// Returns the number of columns for a number of windows in an area.
+    fn columns(&self, n: usize, area: Rect) -> usize {
+        let columns = match self.columns {
+            GridColumns::Square => (n as f64).sqrt().ceil() as usize,
+            GridColumns::Fixed(columns) => columns,
+            GridColumns::Aspect => {
+                let aspect = area.width.max(1) as f64 / area.height.max(1) as f64;
+                (n as f64 * aspect).sqrt().round() as usize
+            }
+        };
+        columns.clamp(1, n.max(1))
     }
-    fn changewin(
+}
+
+impl Default for Grid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layout for Grid {
+    fn boxed_clone(&self) -> Option<Box<dyn Layout>> {
+        Some(Box::new(self.clone()))
+    }
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn allow_motions(&self) -> bool {
+        false
+    }
+
+    fn border_width(&self) -> u16 {
+        self.borders
+    }
+
+    fn size_hints(&self) -> bool {
+        self.size_hints
+    }
+
+    fn handle_message(&mut self, message: &LayoutMessage) {
+        change_size(&mut self.gaps, message, true);
+        change_size(&mut self.borders, message, false);
+    }
+
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::layouts::*;
+    /// use xcb::{x, XidNew};
+    /// let windows: Vec<x::Window> = (1..=5).map(|id| unsafe { x::Window::new(id) }).collect();
+    /// let mut grid = Grid::new();
+    /// grid.gaps = 0;
+    /// grid.borders = 0;
+    /// // 3 columns, and the 2 windows of the last row share its width.
+    /// let arrangement = grid.arrange(Rect::new(0, 0, 900, 600), &windows, None);
+    /// assert_eq!(arrangement[2].1, Rect::new(600, 0, 300, 300));
+    /// assert_eq!(arrangement[3].1, Rect::new(0, 300, 450, 300));
+    /// assert_eq!(arrangement[4].1, Rect::new(450, 300, 450, 300));
+    ///
+    /// grid.columns = GridColumns::Fixed(1);
+    /// let arrangement = grid.arrange(Rect::new(0, 0, 900, 600), &windows, None);
+    /// assert_eq!(arrangement[4].1, Rect::new(0, 480, 900, 120));
+    /// ```
+    fn arrange(
         &self,
-        _windows: &mut Iter<x::Window>,
-        _number: usize,
-        _con: &Connection,
-        _width: u16,
-        _height: u16,
-        _x: i16,
-        _y: i16,
-    ) {
+        area: Rect,
+        windows: &[x::Window],
+        _focused: Option<usize>,
+    ) -> Vec<(x::Window, Rect)> {
+        if windows.is_empty() {
+            return Vec::new();
+        }
+        let gaps = self.gaps as i64;
+        let borders = self.borders as i64;
+        let columns = self.columns(windows.len(), area);
+        let n_rows = windows.len().div_ceil(columns);
+        let rows = split(area.y as i64, area.height as i64, n_rows, gaps);
+        windows
+            .chunks(columns)
+            .zip(rows)
+            .flat_map(|(row, (row_y, row_height))| {
+                let cells = split(area.x as i64, area.width as i64, row.len(), gaps);
+                row.iter()
+                    .zip(cells)
+                    .map(move |(window, (cell_x, cell_width))| {
+                        let rect = fill_cell(cell_x, row_y, cell_width, row_height, borders);
+                        (*window, rect)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect()
     }
 }
 
@@ -394,12 +733,15 @@ impl Layout for Maximized {
 ///     name: "tile",
 ///     borders: 4,
 ///     master_factor: 1.0 / 2.0,
+///     nmaster: 1,
 ///     gaps: 4,
+///     size_hints: true,
 /// };
 /// let max = Maximized {
 ///     name: "max",
 ///     borders: 4,
 ///     gaps: 4,
+///     size_hints: false,
 /// };
 /// let float = Floating {
 ///     name: "float",
@@ -87,7 +87,7 @@
 //!         (&[MODKEY], "n", lazy! {Lapin::spawn("chromium")}),
 //!         (&[MODKEY], "a", lazy! {Lapin::spawn("rofi -show run")}),
 //!         // kill focus
-//!         (&[MODKEY], "w", lazy! {wm, wm.killfocused()}),
+//!         (&[MODKEY], "w", lazy! {wm, wm.close_focused()}),
 //!         // change focus
 //!         (&[MODKEY], "j", lazy! {wm, wm.nextwin()}),
 //!         (&[MODKEY], "k", lazy! {wm, wm.prevwin()}),
@@ -102,7 +102,7 @@
 //!         // toggle ool
 //!         (&[MODKEY, "Shift"], "t", lazy! {wm, wm.toggle_ool()}),
 //!         // fullscreen
-//!         (&[MODKEY, "Shift"], "f", lazy! {wm, wm.fullscreen()}),
+//!         (&[MODKEY, "Shift"], "f", lazy! {wm, wm.toggle_fullscreen()}),
 //!         // change focused screen (monitor)
 //!         (&[MODKEY], "y", lazy! {wm, wm.prev_screen()}),
 //!         (&[MODKEY], "u", lazy! {wm, wm.next_screen()}),
@@ -144,17 +144,26 @@
 //! ```
 
 pub mod config;
+mod docks;
+mod ewmh;
+pub mod ipc;
 pub mod keys;
 pub mod lapin_api;
 pub mod layouts;
 pub mod rules;
 pub mod screens;
+pub mod size_hints;
+pub mod spawn;
+pub mod state;
 pub mod utils;
 
 use config::*;
+use ipc::Event;
 use keys::*;
 use rules::*;
 use screens::*;
+use size_hints::SizeHints;
+use std::collections::HashMap;
 use std::time;
 use xcb::x;
 use xcb::Connection;
@@ -173,10 +182,34 @@ xcb::atoms_struct! {
         pub net_wm_name => b"_NET_WM_NAME" only_if_exists = false,
         pub net_wm_state => b"_NET_WM_STATE" only_if_exists = false,
         pub net_wm_state_fullscreen => b"_NET_WM_STATE_FULLSCREEN" only_if_exists = false,
+        pub net_wm_state_maximized_vert => b"_NET_WM_STATE_MAXIMIZED_VERT" only_if_exists = false,
+        pub net_wm_state_maximized_horz => b"_NET_WM_STATE_MAXIMIZED_HORZ" only_if_exists = false,
+        pub net_wm_state_above => b"_NET_WM_STATE_ABOVE" only_if_exists = false,
+        pub net_wm_state_below => b"_NET_WM_STATE_BELOW" only_if_exists = false,
+        pub net_wm_state_sticky => b"_NET_WM_STATE_STICKY" only_if_exists = false,
+        pub net_wm_state_hidden => b"_NET_WM_STATE_HIDDEN" only_if_exists = false,
+        pub net_wm_state_demands_attention => b"_NET_WM_STATE_DEMANDS_ATTENTION" only_if_exists = false,
 	pub net_wm_action_fullscreen => b"_NET_WM_ACTION_FULLSCREEN" only_if_exists = false,
         pub net_wm_desktop => b"_NET_WM_DESKTOP" only_if_exists = false,
+        pub net_wm_pid => b"_NET_WM_PID" only_if_exists = false,
+        pub net_startup_id => b"_NET_STARTUP_ID" only_if_exists = false,
+        pub utf8_string => b"UTF8_STRING" only_if_exists = false,
         pub net_wm_window_type => b"_NET_WM_WINDOW_TYPE" only_if_exists = false,
+        pub net_wm_window_type_desktop => b"_NET_WM_WINDOW_TYPE_DESKTOP" only_if_exists = false,
+        pub net_wm_window_type_dock => b"_NET_WM_WINDOW_TYPE_DOCK" only_if_exists = false,
+        pub net_wm_window_type_toolbar => b"_NET_WM_WINDOW_TYPE_TOOLBAR" only_if_exists = false,
+        pub net_wm_window_type_menu => b"_NET_WM_WINDOW_TYPE_MENU" only_if_exists = false,
+        pub net_wm_window_type_utility => b"_NET_WM_WINDOW_TYPE_UTILITY" only_if_exists = false,
+        pub net_wm_window_type_splash => b"_NET_WM_WINDOW_TYPE_SPLASH" only_if_exists = false,
         pub net_wm_window_type_dialog => b"_NET_WM_WINDOW_TYPE_DIALOG" only_if_exists = false,
+        pub net_wm_window_type_dropdown_menu => b"_NET_WM_WINDOW_TYPE_DROPDOWN_MENU" only_if_exists = false,
+        pub net_wm_window_type_popup_menu => b"_NET_WM_WINDOW_TYPE_POPUP_MENU" only_if_exists = false,
+        pub net_wm_window_type_tooltip => b"_NET_WM_WINDOW_TYPE_TOOLTIP" only_if_exists = false,
+        pub net_wm_window_type_notification => b"_NET_WM_WINDOW_TYPE_NOTIFICATION" only_if_exists = false,
+        pub net_wm_window_type_combo => b"_NET_WM_WINDOW_TYPE_COMBO" only_if_exists = false,
+        pub net_wm_window_type_dnd => b"_NET_WM_WINDOW_TYPE_DND" only_if_exists = false,
+        pub net_wm_window_type_normal => b"_NET_WM_WINDOW_TYPE_NORMAL" only_if_exists = false,
+        pub wm_window_role => b"WM_WINDOW_ROLE" only_if_exists = false,
         pub net_client_list => b"_NET_CLIENT_LIST" only_if_exists = false,
 	pub net_number_of_desktops => b"_NET_NUMBER_OF_DESKTOPS" only_if_exists = false,
 	pub net_desktop_geometry => b"_NET_DESKTOP_GEOMETRY" only_if_exists = false,
@@ -184,10 +217,50 @@ xcb::atoms_struct! {
 	pub net_current_desktop => b"_NET_CURRENT_DESKTOP" only_if_exists = false,
 	pub net_desktop_names => b"_NET_DESKTOP_NAMES" only_if_exists = false,
 	pub net_workarea => b"_NET_WORKAREA" only_if_exists = false,
+        pub net_wm_strut => b"_NET_WM_STRUT" only_if_exists = false,
+        pub net_wm_strut_partial => b"_NET_WM_STRUT_PARTIAL" only_if_exists = false,
 	pub net_supporting_wm_check => b"_NET_SUPPORTING_WM_CHECK" only_if_exists = false,
     }
 }
 
+/// Position and length of a window axis before maximizing it.
+type MaximizedAxis = Option<(i16, u16)>;
+
+/// Placement of a window before it went fullscreen, so it can be
+/// restored.
+struct FullscreenRestore {
+    /// If it was an ool window.
+    ool: bool,
+    /// Position in the stack of the workspace.
+    index: usize,
+    /// Geometry as (x, y, width, height, border width).
+    geometry: (i16, i16, u16, u16, u16),
+}
+
+/// Why a workspace is re-tiled, to call the matching method of layouts
+/// that don't implement `Layout::arrange()`.
+enum Retile {
+    NewWindow,
+    /// With the index of the focused window.
+    DeletedWindow(Option<usize>),
+    /// With the index of the newly focused window.
+    FocusChanged(usize),
+    Reload,
+}
+
+/// A program spawned to have its windows placed in some workspace.
+struct SpawnRecord {
+    pid: u32,
+    startup_id: String,
+    screen: usize,
+    workspace: usize,
+    time: time::Instant,
+}
+
+/// How long a spawned program has to map a window to be placed in the
+/// workspace it was spawned for.
+const SPAWN_RECORD_LIFETIME: time::Duration = time::Duration::from_secs(60);
+
 /// The window manager I suppose.
 pub struct Lapin {
     /// The connection with the X server via the XCB crate. Only touch
@@ -204,6 +277,37 @@ pub struct Lapin {
     pub atoms: Atoms,
     current_scr: usize,
     root: x::Window,
+    /// Windows that follow their screen through workspaces.
+    sticky: Vec<x::Window>,
+    /// Windows kept unmapped because of `_NET_WM_STATE_HIDDEN`.
+    hidden: Vec<x::Window>,
+    /// Windows demanding attention.
+    urgent: Vec<x::Window>,
+    /// Geometry of maximized windows before maximizing, as (x, width) and
+    /// (y, height).
+    maximized: HashMap<x::Window, (MaximizedAxis, MaximizedAxis)>,
+    /// Where fullscreen windows came from.
+    fullscreen: HashMap<x::Window, FullscreenRestore>,
+    /// Windows asked to close with WM_DELETE_WINDOW, and when.
+    closing: HashMap<x::Window, time::Instant>,
+    /// Programs spawned to be placed in some workspace.
+    spawn_records: Vec<SpawnRecord>,
+    /// The socket to receive commands, if listening.
+    ipc: Option<ipc::IpcServer>,
+    /// Border widths set by rules.
+    border_widths: HashMap<x::Window, u32>,
+    /// Border colors set by rules.
+    border_colors: HashMap<x::Window, u32>,
+    /// Late rules already applied to each window, by index.
+    late_rules_applied: HashMap<x::Window, Vec<usize>>,
+    /// Properties read for the rules of windows that still have late rules
+    /// to match, so only what changed is read again.
+    rule_properties: HashMap<x::Window, WindowProperties>,
+    /// Docks mapped, which aren't managed but reserve space.
+    docks: Vec<x::Window>,
+    /// Window whose manage hooks are running, not announced to IPC
+    /// subscribers yet.
+    unannounced: Option<x::Window>,
 }
 
 impl Lapin {
@@ -226,15 +330,6 @@ impl Lapin {
         None
     }
 
-    fn add_border(&self, w: x::Window) {
-        self.x_connection.send_request(&x::ConfigureWindow {
-            window: w,
-            value_list: &[x::ConfigWindow::BorderWidth(
-                self.current_layout().border_width() as u32,
-            )],
-        });
-    }
-
     fn color_focused_border(&self, w: x::Window) {
         self.x_connection.send_request(&x::ChangeWindowAttributes {
             window: w,
@@ -243,9 +338,17 @@ impl Lapin {
     }
 
     fn restore_border(&self, window: x::Window) {
+        let color = if self.urgent.contains(&window) {
+            self.config.border_color_urgent
+        } else {
+            self.border_colors
+                .get(&window)
+                .copied()
+                .unwrap_or(self.config.border_color)
+        };
         self.x_connection.send_request(&x::ChangeWindowAttributes {
             window,
-            value_list: &[x::Cw::BorderPixel(self.config.border_color)],
+            value_list: &[x::Cw::BorderPixel(color)],
         });
     }
 
@@ -259,84 +362,603 @@ impl Lapin {
         });
     }
 
-    /// Apply rules for a window, returns what must be done with it (add_border, ool, workspace).
-    fn apply_rules(&self, window: x::Window) -> (bool, bool, usize) {
-        let mut add_border = true;
-        let mut ool = false;
-        let mut workspace = self.current_screen().current_wk;
+    /// Applies the rules for a window, returning where and how to place it.
+    fn apply_rules(&mut self, window: x::Window) -> Placement {
+        let mut placement = Placement::default();
+        if self.config.rules.is_empty() {
+            return placement;
+        }
+        let properties = self.window_properties(window);
+
+        let mut late = Vec::new();
+        for (i, rule) in self.config.rules.iter().enumerate() {
+            if rule.property.matches(&properties) {
+                for apply in rule.apply.iter() {
+                    placement.apply(apply);
+                }
+                if rule.late {
+                    late.push(i);
+                }
+            }
+        }
+        let pending = self.config.rules.iter().filter(|rule| rule.late).count() > late.len();
+        // late rules that already matched don't apply again.
+        if !late.is_empty() {
+            self.late_rules_applied.insert(window, late);
+        }
+        if pending {
+            self.rule_properties.insert(window, properties);
+        }
+
+        placement
+    }
 
-        let (class1, class2) = if let Some(t) = self.get_class(window) {
-            t
+    /// Applies the late rules that weren't applied to a window yet. Called
+    /// when its title (or else its class) changes, reading only what
+    /// changed.
+    fn apply_late_rules(&mut self, window: x::Window, title: bool) {
+        let applied = self.late_rules_applied.get(&window);
+        let pending = self.config.rules.iter().enumerate().any(|(i, rule)| {
+            rule.late
+                && !applied.is_some_and(|applied| applied.contains(&i))
+                && if title {
+                    rule.property.uses_title()
+                } else {
+                    rule.property.uses_class()
+                }
+        });
+        if !pending {
+            return;
+        }
+        let Some((s, k, _, ool)) = self.window_location(window) else {
+            return;
+        };
+        let mut properties = match self.rule_properties.remove(&window) {
+            Some(properties) => properties,
+            None => self.window_properties(window),
+        };
+        if title {
+            properties.title = self.get_title(window);
         } else {
-            return (add_border, ool, workspace);
+            (properties.instance, properties.class) = self.get_class(window).unzip();
+        }
+
+        let mut placement = Placement::default();
+        let applied = self.late_rules_applied.entry(window).or_default();
+        let mut matched = false;
+        for (i, rule) in self.config.rules.iter().enumerate() {
+            if rule.late && !applied.contains(&i) && rule.property.matches(&properties) {
+                applied.push(i);
+                matched = true;
+                for apply in rule.apply.iter() {
+                    placement.apply(apply);
+                }
+            }
+        }
+        if self.config.rules.iter().filter(|rule| rule.late).count() > applied.len() {
+            self.rule_properties.insert(window, properties);
+        }
+        if !matched {
+            return;
+        }
+
+        if let Some(width) = placement.border_width {
+            self.border_widths.insert(window, width);
+        }
+        if let Some(color) = placement.border_color {
+            self.border_colors.insert(window, color);
+            if self.get_focused_window() != Some(window) {
+                self.restore_border(window);
+            }
+        }
+
+        let mut to_s = s;
+        let mut to_k = k;
+        if let Some(screen) = placement.screen.filter(|n| *n < self.screens.len()) {
+            if screen != s {
+                to_s = screen;
+                to_k = self.screens[screen].current_wk;
+            }
+        }
+        if let Some(workspace) = placement
+            .workspace
+            .filter(|k| *k < self.screens[to_s].workspaces.len())
+        {
+            to_k = workspace;
+        }
+        let to_ool = ool || placement.float;
+        self.move_window(window, to_s, to_k, to_ool);
+
+        if let Some(name) = placement.layout {
+            if let Some(l) = self.screens[to_s].workspaces[to_k].layout_position(name) {
+                if l != self.screens[to_s].workspaces[to_k].layout {
+                    self.set_layout(to_s, to_k, l);
+                }
+            }
+        }
+        if to_ool {
+            self.place_floating(window, to_s, to_k, &placement);
+        }
+        if placement.fullscreen {
+            self.set_fullscreen(window, true);
+        }
+        if placement.sticky {
+            self.set_sticky(window, true);
+        }
+        if placement.switch_to_workspace
+            && (to_s != self.current_scr || to_k != self.current_screen().current_wk)
+        {
+            if to_s != self.current_scr {
+                self.focus_screen(to_s);
+            } else if let Some(old_win) = self.get_focused_window() {
+                self.restore_border(old_win);
+            }
+            self.goto_workspace(to_k);
+            if !placement.no_focus {
+                self.toggle_focus(window, true);
+            }
+        }
+        self.x_connection.flush().ok();
+    }
+
+    /// Moves a managed window to a workspace of any screen, in or out of
+    /// the layout, re-tiling and fixing the focus as needed.
+    fn move_window(&mut self, window: x::Window, to_s: usize, to_k: usize, to_ool: bool) {
+        let Some((s, k, _, ool)) = self.window_location(window) else {
+            return;
         };
+        if (s, k, ool) == (to_s, to_k, to_ool) {
+            return;
+        }
+        let was_focused = self.get_focused_window() == Some(window);
+        let visible = |lapin: &Lapin, s: usize, k: usize| lapin.screens[s].current_wk == k;
 
-        for rule in self.config.rules.iter() {
-            if rule.property == Property::Class(class1.clone())
-                || rule.property == Property::Class(class2.clone())
-            {
-                match rule.apply {
-                    Apply::Workspace(n) => workspace = n,
-                    Apply::Float => ool = true,
-                    Apply::Fullscreen => {
-                        self.x_connection.send_request(&x::ConfigureWindow {
-                            window,
-                            value_list: &[
-                                x::ConfigWindow::X(self.current_screen().x as i32),
-                                x::ConfigWindow::Y(self.current_screen().y as i32),
-                                x::ConfigWindow::Width(self.current_screen().width as u32),
-                                x::ConfigWindow::Height(self.current_screen().height as u32),
-                            ],
-                        });
-                        self.x_connection.flush().ok();
-                        ool = true;
-                        add_border = false;
-                        self.x_connection.send_request(&x::ChangeProperty {
-                            mode: x::PropMode::Replace,
-                            window: window,
-                            property: self.atoms.net_wm_state,
-                            r#type: x::ATOM_ATOM,
-                            data: &[self.atoms.net_wm_state_fullscreen],
-                        });
-                    }
+        self.screens[s].workspaces[k].remove_window(window);
+        self.screens[to_s].workspaces[to_k].insert_window(window, to_ool);
+
+        let border = if to_ool {
+            self.config.border_width
+        } else {
+            self.screens[to_s].workspaces[to_k]
+                .current_layout()
+                .border_width() as u32
+        };
+        self.x_connection.send_request(&x::ConfigureWindow {
+            window,
+            value_list: &[x::ConfigWindow::BorderWidth(
+                self.border_width_for(window, border),
+            )],
+        });
+
+        if (s, k) != (to_s, to_k) {
+            if visible(self, to_s, to_k) {
+                if !self.hidden.contains(&window) {
+                    self.x_connection.send_request(&x::MapWindow { window });
                 }
+            } else {
+                self.x_connection.send_request(&x::UnmapWindow { window });
             }
+            self.x_connection.send_request(&x::ChangeProperty {
+                mode: x::PropMode::Replace,
+                window,
+                property: self.atoms.net_wm_desktop,
+                r#type: x::ATOM_CARDINAL,
+                data: &[to_k as u32],
+            });
+        }
+        if visible(self, s, k) {
+            self.reload_screen(s);
         }
+        if to_s != s && visible(self, to_s, to_k) {
+            self.reload_screen(to_s);
+        }
+
+        if was_focused && (s, k) == (to_s, to_k) {
+            // just went in or out of the layout.
+            self.toggle_focus(window, true);
+        } else if was_focused {
+            match self.get_focused_window() {
+                Some(focused) if focused != window => {
+                    self.restore_border(window);
+                    self.toggle_focus(focused, false);
+                }
+                Some(_) => {}
+                None => {
+                    self.restore_border(window);
+                    self.x_connection.send_request(&x::SetInputFocus {
+                        revert_to: x::InputFocus::PointerRoot,
+                        focus: self.root,
+                        time: x::CURRENT_TIME,
+                    });
+                    self.emit_focus();
+                }
+            }
+        }
+        self.x_connection.flush().ok();
+    }
+
+    /// Border width of a window: the one set by rules, or the default.
+    fn border_width_for(&self, window: x::Window, default: u32) -> u32 {
+        self.border_widths.get(&window).copied().unwrap_or(default)
+    }
 
-        return (add_border, ool, workspace);
+    /// Returns the size closest to `width` and `height` allowed by the size
+    /// hints of a window.
+    fn constrain_size(&self, window: x::Window, width: u32, height: u32) -> (u32, u32) {
+        match SizeHints::get(&self.x_connection, window) {
+            Some(hints) => hints.constrain(width, height),
+            None => (width, height),
+        }
+    }
+
+    /// Checks if a window should float regardless of rules, i.e., if it's
+    /// transient or one of the types in `Config::float_window_types`.
+    fn should_auto_float(&self, window: x::Window, transient: bool) -> bool {
+        if transient && self.config.float_transients {
+            return true;
+        }
+        // the first type is the preferred one.
+        self.get_window_types(window)
+            .first()
+            .is_some_and(|window_type| self.config.float_window_types.contains(window_type))
+    }
+
+    /// Changes the layout of a workspace, re-tiling it if it's visible.
+    fn set_layout(&mut self, s: usize, k: usize, l: usize) {
+        self.screens[s].workspaces[k].layout = l;
+        let border = self.screens[s].workspaces[k].layouts[l].border_width() as u32;
+        for window in self.screens[s].workspaces[k].windows.iter() {
+            self.x_connection.send_request(&x::ConfigureWindow {
+                window: *window,
+                value_list: &[x::ConfigWindow::BorderWidth(
+                    self.border_width_for(*window, border),
+                )],
+            });
+        }
+        if k == self.screens[s].current_wk {
+            self.reload_screen(s);
+        }
+        self.x_connection.flush().ok();
+        self.emit(Event::Layout {
+            screen: s,
+            workspace: k,
+            layout: self.screens[s].workspaces[k].layouts[l].name().to_string(),
+        });
+    }
+
+    /// Moves and resizes a floating window as rules asked, in screen `s`
+    /// and workspace `k`. Transient windows are centered over their parent
+    /// if it's managed, otherwise over the screen.
+    fn place_floating(&self, window: x::Window, s: usize, k: usize, placement: &Placement) {
+        if placement.geometry.is_none() && !placement.center {
+            return;
+        }
+        let screen = &self.screens[s];
+        let (mut x, mut y, width, height) = match placement.geometry {
+            Some(geometry) if geometry.relative => (
+                screen.x as i32 + geometry.x as i32,
+                screen.y as i32 + geometry.y as i32,
+                geometry.width,
+                geometry.height,
+            ),
+            Some(geometry) => (
+                geometry.x as i32,
+                geometry.y as i32,
+                geometry.width,
+                geometry.height,
+            ),
+            None => {
+                let cookie = self.x_connection.send_request(&x::GetGeometry {
+                    drawable: x::Drawable::Window(window),
+                });
+                let Ok(reply) = self.x_connection.wait_for_reply(cookie) else {
+                    return;
+                };
+                (
+                    reply.x() as i32,
+                    reply.y() as i32,
+                    reply.width(),
+                    reply.height(),
+                )
+            }
+        };
+        let (width, height) = self.constrain_size(window, width as u32, height as u32);
+        if placement.center {
+            let layout = screen.workspaces[k].current_layout();
+            let border = self.border_width_for(window, layout.border_width() as u32) as i32;
+            let (outer_width, outer_height) =
+                (width as i32 + border * 2, height as i32 + border * 2);
+            let screen_area = (
+                screen.x as i32,
+                screen.y as i32,
+                screen.width as i32,
+                screen.height as i32,
+            );
+            let parent = self
+                .get_transient_for(window)
+                .filter(|parent| self.window_location(*parent).is_some());
+            let (area_x, area_y, area_width, area_height) = parent
+                .and_then(|parent| {
+                    let cookie = self.x_connection.send_request(&x::GetGeometry {
+                        drawable: x::Drawable::Window(parent),
+                    });
+                    let reply = self.x_connection.wait_for_reply(cookie).ok()?;
+                    let border = reply.border_width() as i32;
+                    Some((
+                        reply.x() as i32,
+                        reply.y() as i32,
+                        reply.width() as i32 + border * 2,
+                        reply.height() as i32 + border * 2,
+                    ))
+                })
+                .unwrap_or(screen_area);
+            x = area_x + (area_width - outer_width) / 2;
+            y = area_y + (area_height - outer_height) / 2;
+            // don't let a big dialog of a small window go out of the
+            // screen.
+            let (sx, sy, sw, sh) = screen_area;
+            x = x.min(sx + sw - outer_width).max(sx);
+            y = y.min(sy + sh - outer_height).max(sy);
+        }
+        self.x_connection.send_request(&x::ConfigureWindow {
+            window,
+            value_list: &[
+                x::ConfigWindow::X(x),
+                x::ConfigWindow::Y(y),
+                x::ConfigWindow::Width(width),
+                x::ConfigWindow::Height(height),
+            ],
+        });
     }
 
-    /// Calculates size and coordinates for sending to layouts, in the
-    /// format (width, height, x, y).
-    fn calculate_layout_coordinates(&self) -> (u16, u16, i16, i16) {
-        if self.current_workspace().respect_reserved_space {
-            let width = self.current_screen().width
-                - self.config.reserved_space.1
-                - self.config.reserved_space.3;
-            let height = self.current_screen().height
-                - self.config.reserved_space.0
-                - self.config.reserved_space.2;
-            let x = self.current_screen().x + self.config.reserved_space.3 as i16;
-            let y = self.current_screen().y + self.config.reserved_space.0 as i16;
+    /// Calculates size and coordinates of the area of a workspace for
+    /// sending to layouts, in the format (width, height, x, y).
+    fn layout_coordinates(&self, s: usize, k: usize) -> (u16, u16, i16, i16) {
+        let screen = &self.screens[s];
+        if screen.workspaces[k].respect_reserved_space {
+            // docks may reserve more space than the config asks for.
+            let config = self
+                .screen_config(s)
+                .and_then(|config| config.reserved_space)
+                .unwrap_or(self.config.reserved_space);
+            let docks = screen.reserved_space;
+            let top = config.0.max(docks.0);
+            let right = config.1.max(docks.1);
+            let bottom = config.2.max(docks.2);
+            let left = config.3.max(docks.3);
+            let width = screen.width.saturating_sub(right.saturating_add(left));
+            let height = screen.height.saturating_sub(top.saturating_add(bottom));
+            let x = screen.x.saturating_add_unsigned(left);
+            let y = screen.y.saturating_add_unsigned(top);
             (width, height, x, y)
         } else {
-            (
-                self.current_screen().width,
-                self.current_screen().height,
-                self.current_screen().x,
-                self.current_screen().y,
-            )
+            (screen.width, screen.height, screen.x, screen.y)
         }
     }
 
-    fn manage_window(&mut self, ev: x::MapRequestEvent) {
-        // check if we really need to manage the window
-        if self.window_location(ev.window()).is_some() {
+    /// Reloads the layout of the current workspace of a screen.
+    fn reload_screen(&self, s: usize) {
+        self.retile(s, self.screens[s].current_wk, Retile::Reload);
+    }
+
+    /// Same as `retile`, for the current workspace.
+    fn retile_current(&self, reason: Retile) {
+        self.retile(self.current_scr, self.current_screen().current_wk, reason);
+    }
+
+    /// Moves the windows of a workspace to where its layout says, then
+    /// calls the hook of old layouts that don't implement
+    /// `Layout::arrange()`.
+    fn retile(&self, s: usize, k: usize, reason: Retile) {
+        let workspace = &self.screens[s].workspaces[k];
+        let layout = workspace.current_layout();
+        let (width, height, x, y) = self.layout_coordinates(s, k);
+        let area = layouts::Rect::new(x as i32, y as i32, width as u32, height as u32);
+        // hidden windows don't take space in the layout.
+        let windows: Vec<x::Window> = workspace
+            .windows
+            .iter()
+            .copied()
+            .filter(|window| !self.hidden.contains(window))
+            .collect();
+        let visible_index = |w: usize| {
+            let window = workspace.windows.get(w)?;
+            windows.iter().position(|visible| visible == window)
+        };
+        let focused = workspace
+            .focused
+            .filter(|_| !workspace.ool_focus)
+            .and_then(visible_index);
+        let arrangement = layout.arrange(area, &windows, focused);
+        self.apply_arrangement(arrangement, layout.size_hints());
+
+        let con = &self.x_connection;
+        let windows = &mut windows.iter();
+        match reason {
+            Retile::NewWindow => layout.newwin(windows, con, width, height, x, y),
+            Retile::DeletedWindow(current) => {
+                let current = current.and_then(visible_index);
+                layout.delwin(windows, current, con, width, height, x, y)
+            }
+            Retile::FocusChanged(number) => {
+                let number = visible_index(number).unwrap_or(0);
+                layout.changewin(windows, number, con, width, height, x, y)
+            }
+            Retile::Reload => layout.reload(windows, con, width, height, x, y),
+        }
+    }
+
+    /// Moves windows to the geometries computed by a layout, skipping the
+    /// ones already there.
+    fn apply_arrangement(&self, arrangement: Vec<(x::Window, layouts::Rect)>, size_hints: bool) {
+        // ask every geometry and size hints before waiting, so it's a single
+        // round trip.
+        let cookies: Vec<_> = arrangement
+            .iter()
+            .map(|(window, _)| {
+                let geometry = self.x_connection.send_request(&x::GetGeometry {
+                    drawable: x::Drawable::Window(*window),
+                });
+                let hints = size_hints.then(|| SizeHints::request(&self.x_connection, *window));
+                (geometry, hints)
+            })
+            .collect();
+        for ((window, rect), (cookie, hints)) in arrangement.into_iter().zip(cookies) {
+            let hints = hints.and_then(|hints| SizeHints::wait(&self.x_connection, hints));
+            let rect = hints.map_or(rect, |hints| hints.fit(rect));
+            if let Ok(current) = self.x_connection.wait_for_reply(cookie) {
+                let current = layouts::Rect::new(
+                    current.x() as i32,
+                    current.y() as i32,
+                    current.width() as u32,
+                    current.height() as u32,
+                );
+                if current == rect {
+                    continue;
+                }
+            }
+            self.x_connection.send_request(&x::ConfigureWindow {
+                window,
+                value_list: &[
+                    x::ConfigWindow::X(rect.x),
+                    x::ConfigWindow::Y(rect.y),
+                    x::ConfigWindow::Width(rect.width),
+                    x::ConfigWindow::Height(rect.height),
+                ],
+            });
+        }
+    }
+
+    /// Returns the screen containing the center of a window, or the
+    /// current screen if it's not inside any.
+    fn screen_of_window(&self, window: x::Window) -> usize {
+        let cookie = self.x_connection.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        });
+        let Ok(reply) = self.x_connection.wait_for_reply(cookie) else {
+            return self.current_scr;
+        };
+        let x = reply.x() as i32 + (reply.width() / 2) as i32;
+        let y = reply.y() as i32 + (reply.height() / 2) as i32;
+        for (s, screen) in self.screens.iter().enumerate() {
+            if x >= screen.x as i32
+                && x < screen.x as i32 + screen.width as i32
+                && y >= screen.y as i32
+                && y < screen.y as i32 + screen.height as i32
+            {
+                return s;
+            }
+        }
+        self.current_scr
+    }
+
+    /// Manages windows that were already mapped before the window manager
+    /// started, e.g. started by .xinitrc or left over after a restart.
+    fn adopt_windows(&mut self) {
+        let cookie = self
+            .x_connection
+            .send_request(&x::QueryTree { window: self.root });
+        let Ok(tree) = self.x_connection.wait_for_reply(cookie) else {
+            return;
+        };
+        for window in tree.children() {
+            let cookie = self
+                .x_connection
+                .send_request(&x::GetWindowAttributes { window: *window });
+            let Ok(attributes) = self.x_connection.wait_for_reply(cookie) else {
+                continue;
+            };
+            if attributes.override_redirect() || attributes.map_state() != x::MapState::Viewable {
+                continue;
+            }
+            // _NET_WM_DESKTOP may be 0xffffffff for "all desktops".
+            let workspace = self
+                .get_cardinal_property(*window, self.atoms.net_wm_desktop)
+                .map(|wk| wk as usize)
+                .filter(|wk| *wk < self.config.workspaces.len());
+            let screen = self.screen_of_window(*window);
+            self.manage_window(*window, Some(screen), workspace);
+        }
+        for s in 0..self.screens.len() {
+            self.reload_screen(s);
+        }
+        self.x_connection.flush().ok();
+    }
+
+    /// Records the programs spawned since the last call, if they should be
+    /// placed in the workspace they were spawned from.
+    fn track_spawned(&mut self) {
+        let spawned = spawn::take_spawned();
+        if !self.config.spawn_on_origin {
             return;
         }
-        let cookie = self.x_connection.send_request(&x::GetWindowAttributes {
-            window: ev.window(),
+        for (pid, startup_id) in spawned {
+            self.spawn_records.push(SpawnRecord {
+                pid,
+                startup_id,
+                screen: self.current_scr,
+                workspace: self.current_screen().current_wk,
+                time: time::Instant::now(),
+            });
+        }
+    }
+
+    /// Returns the screen and workspace the program owning a window was
+    /// spawned for, if any. Windows are matched by their startup id, or
+    /// by their PID or the PID of one of its parents, so programs spawned
+    /// by a shell are matched too.
+    fn spawn_origin(&mut self, window: x::Window) -> Option<(usize, usize)> {
+        self.spawn_records
+            .retain(|record| record.time.elapsed() < SPAWN_RECORD_LIFETIME);
+        if self.spawn_records.is_empty() {
+            return None;
+        }
+
+        let startup_id = self.get_utf8_property(window, self.atoms.net_startup_id);
+        let mut index = startup_id.and_then(|id| {
+            self.spawn_records
+                .iter()
+                .position(|record| record.startup_id == id)
         });
+        let mut pid = self.get_cardinal_property(window, self.atoms.net_wm_pid);
+        // a few levels are enough for shells and wrapper scripts.
+        for _ in 0..8 {
+            if index.is_some() {
+                break;
+            }
+            let Some(p) = pid else {
+                break;
+            };
+            index = self.spawn_records.iter().position(|record| record.pid == p);
+            pid = spawn::parent_pid(p).filter(|p| *p > 1);
+        }
+
+        let record = self.spawn_records.remove(index?);
+        if record.screen < self.screens.len()
+            && record.workspace < self.screens[record.screen].workspaces.len()
+        {
+            Some((record.screen, record.workspace))
+        } else {
+            None
+        }
+    }
+
+    /// Manages a window. It's placed in the screen and workspace passed as
+    /// arguments, or in the current ones, unless it was spawned for another
+    /// workspace or rules say otherwise.
+    fn manage_window(
+        &mut self,
+        window: x::Window,
+        screen: Option<usize>,
+        workspace: Option<usize>,
+    ) {
+        // check if we really need to manage the window
+        if self.window_location(window).is_some() {
+            return;
+        }
+        let cookie = self
+            .x_connection
+            .send_request(&x::GetWindowAttributes { window });
         let reply = self.x_connection.wait_for_reply(cookie);
         if let Ok(reply) = reply {
             if reply.override_redirect() {
@@ -345,10 +967,14 @@ impl Lapin {
         } else {
             return;
         }
+        if self.get_window_types(window).contains(&WindowType::Dock) {
+            self.manage_dock(window);
+            return;
+        }
 
         // add required attributes
         self.x_connection.send_request(&x::ChangeWindowAttributes {
-            window: ev.window(),
+            window,
             value_list: &[
                 x::Cw::BorderPixel(self.config.border_color),
                 x::Cw::EventMask(
@@ -359,59 +985,357 @@ impl Lapin {
             ],
         });
 
-        let (add_border, ool, workspace) = self.apply_rules(ev.window());
+        let mut placement = self.apply_rules(window);
+        let transient_for = self.get_transient_for(window);
+        let parent_location = transient_for.and_then(|parent| self.window_location(parent));
+        if !placement.float && self.should_auto_float(window, transient_for.is_some()) {
+            placement.float = true;
+            if placement.geometry.is_none() {
+                placement.center = true;
+            }
+        }
+        let (mut s, mut workspace) = match (self.spawn_origin(window), parent_location) {
+            (Some(origin), _) => origin,
+            // dialogs go where their parent is.
+            (None, Some((s, k, _, _))) => (s, k),
+            (None, None) => {
+                let s = screen.unwrap_or(self.current_scr);
+                (s, workspace.unwrap_or(self.screens[s].current_wk))
+            }
+        };
+        if let Some(screen) = placement.screen.filter(|n| *n < self.screens.len()) {
+            if screen != s {
+                s = screen;
+                workspace = self.screens[s].current_wk;
+            }
+        }
+        if let Some(k) = placement
+            .workspace
+            .filter(|k| *k < self.screens[s].workspaces.len())
+        {
+            workspace = k;
+        }
+        let ool = placement.float;
+        // clients may ask for fullscreen before being mapped
+        let fullscreen = placement.fullscreen
+            || self.has_net_wm_state(window, self.atoms.net_wm_state_fullscreen);
 
-        if add_border {
-            self.add_border(ev.window());
+        if let Some(width) = placement.border_width {
+            self.border_widths.insert(window, width);
         }
-        if let Some(old_win) = self.get_focused_window() {
-            self.restore_border(old_win);
+        if let Some(color) = placement.border_color {
+            self.border_colors.insert(window, color);
+            self.restore_border(window);
+        }
+        if let Some(name) = placement.layout {
+            match self.screens[s].workspaces[workspace].layout_position(name) {
+                Some(l) if l != self.screens[s].workspaces[workspace].layout => {
+                    self.set_layout(s, workspace, l)
+                }
+                _ => {}
+            }
+        }
+        if placement.switch_to_workspace
+            && (s != self.current_scr || workspace != self.current_screen().current_wk)
+        {
+            if s != self.current_scr {
+                self.focus_screen(s);
+            }
+            self.goto_workspace(workspace);
         }
 
+        let layout = self.screens[s].workspaces[workspace].current_layout();
+        self.x_connection.send_request(&x::ConfigureWindow {
+            window,
+            value_list: &[x::ConfigWindow::BorderWidth(
+                self.border_width_for(window, layout.border_width() as u32),
+            )],
+        });
         if ool {
-            self.current_screen_mut().workspaces[workspace]
-                .ool_windows
-                .insert(0, ev.window());
+            self.place_floating(window, s, workspace, &placement);
+        }
+
+        // there's no focus to steal in a workspace without focus.
+        let no_focus =
+            placement.no_focus && self.screens[s].workspaces[workspace].focused.is_some();
+        self.screens[s].workspaces[workspace].insert_window(window, ool);
+
+        if s == self.current_scr && workspace == self.current_screen().current_wk {
+            if !no_focus {
+                if let Some(old_win) = self.get_focused_window() {
+                    self.restore_border(old_win);
+                }
+            }
+            self.retile_current(Retile::NewWindow);
+            self.x_connection.send_request(&x::MapWindow { window });
+            if !no_focus {
+                self.set_focus(window, s, workspace, 0, ool, true);
+            }
+        } else if workspace == self.screens[s].current_wk {
+            // visible in another screen
+            self.reload_screen(s);
+            self.x_connection.send_request(&x::MapWindow { window });
         } else {
-            self.current_screen_mut().workspaces[workspace]
-                .windows
-                .insert(0, ev.window());
-        }
-
-        if workspace == self.current_screen().current_wk {
-            let (width, height, x, y) = self.calculate_layout_coordinates();
-            self.current_layout().newwin(
-                &mut self.workspace_windows(),
-                &self.x_connection,
-                width,
-                height,
-                x,
-                y,
-            );
-            self.x_connection.send_request(&x::MapWindow {
-                window: ev.window(),
-            });
-            self.set_focus(
-                ev.window(),
-                self.current_scr,
-                self.current_screen().current_wk,
-                0,
-                ool,
-                true,
-            );
+            // adopted windows may be mapped already
+            self.x_connection.send_request(&x::UnmapWindow { window });
         }
 
         // add the window workspace EWMH hint
         self.x_connection.send_request(&x::ChangeProperty {
             mode: x::PropMode::Replace,
-            window: ev.window(),
+            window,
             property: self.atoms.net_wm_desktop,
             r#type: x::ATOM_CARDINAL,
             data: &[workspace as u32],
         });
-        self.add_client_to_atom(ev.window());
+        self.add_client_to_atom(window);
+
+        if fullscreen {
+            self.set_fullscreen(window, true);
+        }
+        if placement.sticky {
+            self.set_sticky(window, true);
+        }
 
         self.x_connection.flush().ok();
+        self.run_manage_hooks(window);
+        // hooks may have moved the window, or gotten rid of it.
+        if let Some((s, k, _, _)) = self.window_location(window) {
+            self.emit(Event::Manage {
+                window: window.resource_id(),
+                screen: s,
+                workspace: k,
+            });
+        }
+    }
+
+    /// Calls the manage hooks for a window that was just managed, leaving
+    /// it unmanaged if one of them vetoes it.
+    fn run_manage_hooks(&mut self, window: x::Window) {
+        if self.config.manage_hooks.is_empty() {
+            return;
+        }
+        // hooks need the whole window manager, including the config.
+        let mut hooks = std::mem::take(&mut self.config.manage_hooks);
+        self.unannounced = Some(window);
+        let mut vetoed = false;
+        for hook in hooks.iter_mut() {
+            if self.window_location(window).is_none() {
+                // some hook already got rid of it.
+                break;
+            }
+            if !hook(self, window) {
+                vetoed = true;
+                break;
+            }
+        }
+        // hooks may add hooks.
+        hooks.append(&mut self.config.manage_hooks);
+        self.config.manage_hooks = hooks;
+
+        if vetoed && self.window_location(window).is_some() {
+            self.unmanage_window(window, true);
+            // leave it as it was before we managed it.
+            self.x_connection.send_request(&x::ChangeWindowAttributes {
+                window,
+                value_list: &[x::Cw::EventMask(x::EventMask::NO_EVENT)],
+            });
+            // the border pixmap of the parent, i.e. CopyFromParent. Sent
+            // alone as it fails if the depths differ.
+            self.x_connection.send_request(&x::ChangeWindowAttributes {
+                window,
+                value_list: &[x::Cw::BorderPixmap(x::Pixmap::none())],
+            });
+            self.x_connection.send_request(&x::ConfigureWindow {
+                window,
+                value_list: &[x::ConfigWindow::BorderWidth(0)],
+            });
+            self.x_connection.send_request(&x::DeleteProperty {
+                window,
+                property: self.atoms.net_wm_desktop,
+            });
+            // it asked to be mapped, even if it went to another workspace.
+            self.x_connection.send_request(&x::MapWindow { window });
+            self.x_connection.flush().ok();
+        }
+        self.unannounced = None;
+    }
+
+    /// Handles a configure request. Unmanaged windows get the request
+    /// passed straight through, ool windows and windows in layouts that
+    /// allow motions may move and resize themselves, and tiled windows
+    /// are just told the geometry the layout already assigned to them.
+    fn configure_request(&self, ev: x::ConfigureRequestEvent) {
+        let window = ev.window();
+        let (managed, allowed) = match self.window_location(window) {
+            Some((s, k, _, ool)) => {
+                let layout = self.screens[s].workspaces[k].current_layout();
+                let free = ool || layout.allow_motions();
+                (true, free && !self.fullscreen.contains_key(&window))
+            }
+            None => (false, true),
+        };
+
+        if !allowed {
+            self.send_configure_notify(window);
+            self.x_connection.flush().ok();
+            return;
+        }
+
+        // the list must follow the order of the mask bits.
+        let mask = ev.value_mask();
+        let mut list = Vec::with_capacity(7);
+        if mask.contains(x::ConfigWindowMask::X) {
+            list.push(x::ConfigWindow::X(ev.x() as i32));
+        }
+        if mask.contains(x::ConfigWindowMask::Y) {
+            list.push(x::ConfigWindow::Y(ev.y() as i32));
+        }
+        let resize = mask.intersects(x::ConfigWindowMask::WIDTH | x::ConfigWindowMask::HEIGHT);
+        let hints = if managed && resize {
+            SizeHints::get(&self.x_connection, window)
+        } else {
+            None
+        };
+        if let Some(hints) = hints {
+            // the size hints depend on both sides, so the one that isn't
+            // changing is needed too.
+            let cookie = self.x_connection.send_request(&x::GetGeometry {
+                drawable: x::Drawable::Window(window),
+            });
+            if let Ok(reply) = self.x_connection.wait_for_reply(cookie) {
+                let width = if mask.contains(x::ConfigWindowMask::WIDTH) {
+                    ev.width()
+                } else {
+                    reply.width()
+                };
+                let height = if mask.contains(x::ConfigWindowMask::HEIGHT) {
+                    ev.height()
+                } else {
+                    reply.height()
+                };
+                let (width, height) = hints.constrain(width as u32, height as u32);
+                list.push(x::ConfigWindow::Width(width));
+                list.push(x::ConfigWindow::Height(height));
+            }
+        } else {
+            if mask.contains(x::ConfigWindowMask::WIDTH) {
+                list.push(x::ConfigWindow::Width(ev.width() as u32));
+            }
+            if mask.contains(x::ConfigWindowMask::HEIGHT) {
+                list.push(x::ConfigWindow::Height(ev.height() as u32));
+            }
+        }
+        // borders of managed windows are our business.
+        if !managed && mask.contains(x::ConfigWindowMask::BORDER_WIDTH) {
+            list.push(x::ConfigWindow::BorderWidth(ev.border_width() as u32));
+        }
+        if mask.contains(x::ConfigWindowMask::SIBLING) {
+            list.push(x::ConfigWindow::Sibling(ev.sibling()));
+        }
+        if mask.contains(x::ConfigWindowMask::STACK_MODE) {
+            list.push(x::ConfigWindow::StackMode(ev.stack_mode()));
+        }
+        self.x_connection.send_request(&x::ConfigureWindow {
+            window,
+            value_list: &list,
+        });
+        // ICCCM says we must notify the client even when the request is
+        // honored, as the server may not generate a real event (e.g.
+        // when nothing changed).
+        self.send_configure_notify(window);
+        self.x_connection.flush().ok();
+    }
+
+    /// Sends a synthetic ConfigureNotify to a window with its current
+    /// geometry.
+    fn send_configure_notify(&self, window: x::Window) {
+        let cookie = self.x_connection.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        });
+        let Ok(reply) = self.x_connection.wait_for_reply(cookie) else {
+            return;
+        };
+        let event = x::ConfigureNotifyEvent::new(
+            window,
+            window,
+            x::WINDOW_NONE,
+            reply.x(),
+            reply.y(),
+            reply.width(),
+            reply.height(),
+            reply.border_width(),
+            false,
+        );
+        self.x_connection.send_request(&x::SendEvent {
+            propagate: false,
+            destination: x::SendEventDest::Window(window),
+            event_mask: x::EventMask::STRUCTURE_NOTIFY,
+            event: &event,
+        });
+    }
+
+    /// Asks a window to close itself with WM_DELETE_WINDOW if it supports
+    /// it. Kills the client if it doesn't, or if it was already asked to.
+    fn close_window(&mut self, window: x::Window) {
+        let protocols = self.get_atom_list_property(window, self.atoms.wm_protocols);
+        if self.closing.contains_key(&window) || !protocols.contains(&self.atoms.wm_del_window) {
+            self.kill_window(window);
+            return;
+        }
+        let event = x::ClientMessageEvent::new(
+            window,
+            self.atoms.wm_protocols,
+            x::ClientMessageData::Data32([
+                self.atoms.wm_del_window.resource_id(),
+                x::CURRENT_TIME,
+                0,
+                0,
+                0,
+            ]),
+        );
+        self.x_connection.send_request(&x::SendEvent {
+            propagate: false,
+            destination: x::SendEventDest::Window(window),
+            event_mask: x::EventMask::NO_EVENT,
+            event: &event,
+        });
+        self.closing.insert(window, time::Instant::now());
+        self.x_connection.flush().ok();
+    }
+
+    /// Kills the client owning a window.
+    fn kill_window(&mut self, window: x::Window) {
+        self.closing.remove(&window);
+        self.x_connection.send_request(&x::KillClient {
+            resource: window.resource_id(),
+        });
+        self.x_connection.flush().ok();
+    }
+
+    /// Kills the windows that didn't close in time after being asked to.
+    fn kill_unresponsive_windows(&mut self) {
+        let Some(timeout) = self.config.close_timeout else {
+            return;
+        };
+        let expired: Vec<x::Window> = self
+            .closing
+            .iter()
+            .filter(|(_, asked)| asked.elapsed() >= timeout)
+            .map(|(window, _)| *window)
+            .collect();
+        for window in expired {
+            self.kill_window(window);
+        }
+    }
+
+    /// Time until the next unresponsive window must be killed.
+    fn next_close_timeout(&self) -> Option<time::Duration> {
+        let timeout = self.config.close_timeout?;
+        self.closing
+            .values()
+            .map(|asked| timeout.saturating_sub(asked.elapsed()))
+            .min()
     }
 
     fn reset_focus_after_removing(&mut self, s: usize, k: usize, w: usize, ool: bool) {
@@ -423,6 +1347,7 @@ impl Lapin {
             true
         } else {
             self.current_workspace_mut().focused = None;
+            self.emit_focus();
             return;
         };
 
@@ -437,34 +1362,52 @@ impl Lapin {
         } else {
             self.current_workspace().windows[w]
         };
+        if self.hidden.contains(&window) {
+            self.current_workspace_mut().focused = Some(w);
+            self.current_workspace_mut().ool_focus = ool;
+            self.focus_off_hidden(s, k);
+            return;
+        }
         self.set_focus(window, s, k, w, ool, true);
     }
 
     fn unmanage_window(&mut self, window: x::Window, set_focus: bool) {
         if let Some((s, k, w, ool)) = self.window_location(window) {
+            // subscribers don't hear about windows vetoed by manage hooks.
+            if self.unannounced != Some(window) {
+                self.emit(Event::Unmanage {
+                    window: window.resource_id(),
+                });
+            }
+            self.sticky.retain(|win| *win != window);
+            self.hidden.retain(|win| *win != window);
+            self.urgent.retain(|win| *win != window);
+            self.maximized.remove(&window);
+            self.fullscreen.remove(&window);
+            self.closing.remove(&window);
+            self.border_widths.remove(&window);
+            self.border_colors.remove(&window);
+            self.late_rules_applied.remove(&window);
+            self.rule_properties.remove(&window);
+
+            // windows outside the current workspace don't mess with the
+            // focus.
+            if s != self.current_scr || k != self.current_screen().current_wk {
+                self.screens[s].workspaces[k].remove_window(window);
+                self.update_client_list();
+                if !ool && k == self.screens[s].current_wk {
+                    self.reload_screen(s);
+                }
+                self.x_connection.flush().ok();
+                return;
+            }
+
             if ool {
                 self.current_workspace_mut().ool_windows.remove(w);
             } else {
                 self.current_workspace_mut().windows.remove(w);
             }
-            self.x_connection.send_request(&x::ChangeProperty::<u8> {
-                mode: x::PropMode::Replace,
-                window: self.root,
-                property: self.atoms.net_client_list,
-                r#type: x::ATOM_WINDOW,
-                data: &[],
-            });
-            self.x_connection.flush().ok();
-            for scr in &self.screens {
-                for wk in &scr.workspaces {
-                    for window in &wk.windows {
-                        self.add_client_to_atom(*window);
-                    }
-                    for window in &wk.ool_windows {
-                        self.add_client_to_atom(*window);
-                    }
-                }
-            }
+            self.update_client_list();
             if set_focus {
                 self.reset_focus_after_removing(s, k, w, ool);
             } else if let Some(focused) = self.current_workspace().focused {
@@ -493,34 +1436,38 @@ impl Lapin {
                 }
             }
             if !ool {
-                let (width, height, x, y) = self.calculate_layout_coordinates();
-                self.current_layout().delwin(
-                    &mut self.workspace_windows(),
-                    self.current_workspace().focused,
-                    &self.x_connection,
-                    width,
-                    height,
-                    x,
-                    y,
-                );
+                self.retile_current(Retile::DeletedWindow(self.current_workspace().focused));
             } else if !self.current_workspace().ool_focus {
                 if let Some(number) = self.current_workspace().focused {
-                    let (width, height, x, y) = self.calculate_layout_coordinates();
-                    self.current_layout().changewin(
-                        &mut self.workspace_windows(),
-                        number,
-                        &self.x_connection,
-                        width,
-                        height,
-                        x,
-                        y,
-                    );
+                    self.retile_current(Retile::FocusChanged(number));
                 }
             }
             self.x_connection.flush().ok();
         }
     }
 
+    /// Rewrites `_NET_CLIENT_LIST` with all managed windows.
+    fn update_client_list(&self) {
+        self.x_connection.send_request(&x::ChangeProperty::<u8> {
+            mode: x::PropMode::Replace,
+            window: self.root,
+            property: self.atoms.net_client_list,
+            r#type: x::ATOM_WINDOW,
+            data: &[],
+        });
+        self.x_connection.flush().ok();
+        for scr in &self.screens {
+            for wk in &scr.workspaces {
+                for window in &wk.windows {
+                    self.add_client_to_atom(*window);
+                }
+                for window in &wk.ool_windows {
+                    self.add_client_to_atom(*window);
+                }
+            }
+        }
+    }
+
     fn set_focus(
         &mut self,
         window: x::Window,
@@ -530,10 +1477,15 @@ impl Lapin {
         ool: bool,
         raise: bool,
     ) {
+        let old_scr = self.current_scr;
+        let old_wk = self.screens[s].current_wk;
         self.current_scr = s;
         self.current_screen_mut().current_wk = k;
         self.current_workspace_mut().focused = Some(w);
         self.current_workspace_mut().ool_focus = ool;
+        if self.urgent.contains(&window) {
+            self.set_urgent(window, false);
+        }
         self.x_connection.send_request(&x::SetInputFocus {
             revert_to: x::InputFocus::PointerRoot,
             focus: window,
@@ -547,6 +1499,13 @@ impl Lapin {
         }
         self.color_focused_border(window);
         self.x_connection.flush().ok();
+        if s != old_scr {
+            self.emit(Event::Screen { screen: s });
+        }
+        if k != old_wk {
+            self.emit_workspace(s);
+        }
+        self.emit_focus();
     }
 
     fn toggle_focus(&mut self, window: x::Window, raise: bool) {
@@ -600,16 +1559,23 @@ impl Lapin {
             let list = [
                 x::ConfigWindow::X((ev.root_x() - x_diff) as i32),
                 x::ConfigWindow::Y((ev.root_y() - y_diff) as i32),
-                x::ConfigWindow::BorderWidth(self.config.border_width as u32),
+                x::ConfigWindow::BorderWidth(
+                    self.border_width_for(window, self.config.border_width),
+                ),
             ];
             self.x_connection.send_request(&x::ConfigureWindow {
                 window,
                 value_list: &list,
             });
         } else if ev.state().contains(x::KeyButMask::BUTTON3) {
+            let (width, height) = self.constrain_size(
+                window,
+                (ev.root_x() as i32 - x_pos as i32).max(1) as u32,
+                (ev.root_y() as i32 - y_pos as i32).max(1) as u32,
+            );
             let list = [
-                x::ConfigWindow::Width((ev.root_x() - x_pos) as u32),
-                x::ConfigWindow::Height((ev.root_y() - y_pos) as u32),
+                x::ConfigWindow::Width(width),
+                x::ConfigWindow::Height(height),
             ];
             self.x_connection.send_request(&x::ConfigureWindow {
                 window,
@@ -622,103 +1588,93 @@ impl Lapin {
     fn change_win(&mut self, previous: bool) {
         let s = self.current_scr;
         let k = self.current_screen().current_wk;
-        let ool = self.current_workspace().ool_focus;
+        let workspace = self.current_workspace();
+        let Some(w) = workspace.focused else {
+            return;
+        };
+        let ool = workspace.ool_focus;
+        let cycle = self.focus_cycle(s, k);
+        // the focused window may have just been hidden.
+        let current = cycle.iter().position(|(i, o, _)| (*i, *o) == (w, ool));
+        let n = cycle.len();
+        let next = match current {
+            Some(_) if n <= 1 => return,
+            Some(c) if previous => (c + n - 1) % n,
+            Some(c) => (c + 1) % n,
+            None if n == 0 => return,
+            None => 0,
+        };
+        let (new_w, ool, window) = cycle[next];
+        if let Some(old_win) = self.get_focused_window() {
+            self.restore_border(old_win);
+        }
+        self.set_focus(window, s, k, new_w, ool, true);
+        self.x_connection.flush().ok();
+        if !ool {
+            self.retile_current(Retile::FocusChanged(new_w));
+        }
+    }
 
-        if let Some(w) = self.current_workspace().focused {
-            let ool_n = self.current_workspace().ool_windows.len();
-            let win_n = self.current_workspace().windows.len();
-            if win_n + ool_n <= 1 {
-                return;
-            }
+    /// Returns the windows of a workspace that may be focused, in the order
+    /// focus cycles through them: tiled windows and then ool windows,
+    /// skipping hidden ones. Each one comes with its index and if it's ool.
+    fn focus_cycle(&self, s: usize, k: usize) -> Vec<(usize, bool, x::Window)> {
+        let workspace = &self.screens[s].workspaces[k];
+        let tiled = workspace
+            .windows
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (i, false, *w));
+        let ool = workspace
+            .ool_windows
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (i, true, *w));
+        tiled
+            .chain(ool)
+            .filter(|(_, _, window)| !self.hidden.contains(window))
+            .collect()
+    }
 
-            let w = w as i32;
-            let (this, other) = if ool { (ool_n, win_n) } else { (win_n, ool_n) };
-            let new_w = if previous { w - 1 } else { w + 1 };
-            let (new_w, ool) = if new_w < 0 {
-                if other > 0 {
-                    ((other - 1), !ool)
-                } else {
-                    (this - 1, ool)
-                }
-            } else if new_w >= this as i32 {
-                if other > 0 {
-                    (0, !ool)
-                } else {
-                    (0, ool)
-                }
-            } else {
-                (new_w as usize, ool)
-            };
-            let window = if ool {
-                self.current_workspace().ool_windows[new_w]
+    /// Moves the focus of a workspace off a window that was just hidden.
+    fn focus_off_hidden(&mut self, s: usize, k: usize) {
+        if (s, k) == (self.current_scr, self.current_screen().current_wk) {
+            if self.focus_cycle(s, k).is_empty() {
+                self.current_workspace_mut().focused = None;
+                self.x_connection.send_request(&x::SetInputFocus {
+                    revert_to: x::InputFocus::PointerRoot,
+                    focus: self.root,
+                    time: x::CURRENT_TIME,
+                });
+                self.emit_focus();
             } else {
-                self.current_workspace().windows[new_w]
-            };
-            self.restore_border(self.get_focused_window().unwrap());
-            self.set_focus(window, s, k, new_w, ool, true);
-            self.x_connection.flush().ok();
-            if !ool {
-                let (width, height, x, y) = self.calculate_layout_coordinates();
-                self.current_layout().changewin(
-                    &mut self.workspace_windows(),
-                    new_w,
-                    &self.x_connection,
-                    width,
-                    height,
-                    x,
-                    y,
-                );
+                self.change_win(false);
             }
+        } else {
+            let first = self.focus_cycle(s, k).first().copied();
+            let workspace = &mut self.screens[s].workspaces[k];
+            workspace.focused = first.map(|(w, _, _)| w);
+            workspace.ool_focus = first.is_some_and(|(_, ool, _)| ool);
         }
     }
 
     fn change_layout(&mut self, previous: bool) {
+        let n_layouts = self.current_workspace().layouts.len();
         let new_n = if previous {
             if self.current_workspace().layout == 0 {
-                self.config.layouts.len() - 1
+                n_layouts - 1
             } else {
                 self.current_workspace().layout - 1
             }
         } else {
             self.current_workspace().layout + 1
         };
-        let l = if new_n >= self.config.layouts.len() {
-            0
-        } else {
-            new_n
-        };
-
-        self.current_workspace_mut().layout = l;
-
-        if let Some(cur_win) = self.get_focused_window() {
-            for window in self.workspace_windows() {
-                self.add_border(*window);
-                if *window == cur_win {
-                    self.color_focused_border(*window);
-                } else {
-                    self.restore_border(*window);
-                }
-            }
-        }
+        let l = if new_n >= n_layouts { 0 } else { new_n };
 
-        self.x_connection.flush().ok();
-
-        let (width, height, x, y) = self.calculate_layout_coordinates();
-        self.current_layout().reload(
-            &mut self.workspace_windows(),
-            &self.x_connection,
-            width,
-            height,
-            x,
-            y,
-        );
-        self.x_connection.flush().ok();
+        self.set_layout(self.current_scr, self.current_screen().current_wk, l);
     }
 
     fn change_screen(&mut self, previous: bool) {
-        if let Some(old_win) = self.get_focused_window() {
-            self.restore_border(old_win);
-        }
         let new_s = if previous {
             (self.current_scr as isize) - 1
         } else {
@@ -731,7 +1687,14 @@ impl Lapin {
         } else {
             new_s as usize
         };
+        self.focus_screen(new_s);
+    }
 
+    /// Makes a screen the current one, focusing its focused window.
+    fn focus_screen(&mut self, new_s: usize) {
+        if let Some(old_win) = self.get_focused_window() {
+            self.restore_border(old_win);
+        }
         self.current_scr = new_s;
         let window = if let Some(window) = self.get_focused_window() {
             self.color_focused_border(window);
@@ -756,6 +1719,8 @@ impl Lapin {
         });
 
         self.x_connection.flush().ok();
+        self.emit(Event::Screen { screen: new_s });
+        self.emit_focus();
     }
 
     fn change_window_screen(&mut self, previous: bool) {
@@ -788,16 +1753,7 @@ impl Lapin {
             self.restore_border(window);
 
             if !ool {
-                let (width, height, x, y) = self.calculate_layout_coordinates();
-                self.current_layout().delwin(
-                    &mut self.workspace_windows(),
-                    self.current_workspace().focused,
-                    &self.x_connection,
-                    width,
-                    height,
-                    x,
-                    y,
-                );
+                self.retile_current(Retile::DeletedWindow(self.current_workspace().focused));
             }
             self.x_connection.flush().ok();
 
@@ -818,20 +1774,10 @@ impl Lapin {
                 });
                 self.screens[other_screen].workspaces[other_k].ool_focus = true;
             } else {
-                let other_layout = self.screens[other_screen].workspaces[other_k].layout;
                 self.screens[other_screen].workspaces[other_k]
                     .windows
                     .insert(0, window);
-                self.config.layouts[other_layout].newwin(
-                    &mut self.screens[other_screen].workspaces[other_k]
-                        .windows
-                        .iter(),
-                    &self.x_connection,
-                    self.screens[other_screen].width,
-                    self.screens[other_screen].height,
-                    self.screens[other_screen].x,
-                    self.screens[other_screen].y,
-                );
+                self.retile(other_screen, other_k, Retile::NewWindow);
                 self.screens[other_screen].workspaces[other_k].ool_focus = false;
             }
             self.x_connection.flush().ok();
@@ -853,10 +1799,21 @@ impl Lapin {
         let mut last_mouse_change_focus = time::SystemTime::now();
 
         loop {
-            match utils::get_x_event(&self.x_connection) {
+            self.kill_unresponsive_windows();
+            self.handle_ipc();
+            // keybinds, hooks and commands of the last iteration may have
+            // spawned programs. record them before their windows show up.
+            self.track_spawned();
+            let ipc_fds = self.ipc.as_ref().map(|ipc| ipc.fds()).unwrap_or_default();
+            let Some(event) =
+                utils::wait_x_event(&self.x_connection, self.next_close_timeout(), &ipc_fds)
+            else {
+                continue;
+            };
+            match event {
                 x::Event::MapRequest(ev) => {
                     last_map = time::SystemTime::now();
-                    self.manage_window(ev);
+                    self.manage_window(ev.window(), None, None);
                 }
                 x::Event::DestroyNotify(ev) => {
                     last_map = time::SystemTime::now();
@@ -870,6 +1827,14 @@ impl Lapin {
                         false
                     };
                     self.unmanage_window(ev.window(), set_focus);
+                    self.unmanage_dock(ev.window());
+                }
+                x::Event::UnmapNotify(ev) => {
+                    // a hidden dock doesn't reserve space.
+                    self.unmanage_dock(ev.window());
+                }
+                x::Event::ConfigureRequest(ev) => {
+                    self.configure_request(ev);
                 }
                 x::Event::EnterNotify(ev) => {
                     if time::SystemTime::now().duration_since(last_map).unwrap()
@@ -902,59 +1867,29 @@ impl Lapin {
                     }
                 }
                 x::Event::ClientMessage(ev) => {
-                    // LOL THIS IS A BIG WORKAROUND, but xcb really
-                    // doesn't give much support for me to
-                    // implementing this. I made some really
-                    // scientific measurements and came to the
-                    // conclusion that 357 is the magic number for
-                    // fullscreen, and 358 is when it's set idk lol.
-                    if ev.r#type().resource_id() == 357 {
-                        let cookie = self.x_connection.send_request(&x::GetProperty {
-                            delete: false,
-                            window: ev.window(),
-                            property: self.atoms.net_wm_state,
-                            r#type: x::ATOM_ATOM,
-                            long_offset: 0,
-                            long_length: 0,
-                        });
-                        let reply = self
-                            .x_connection
-                            .wait_for_reply(cookie)
-                            .expect("Connection to the X server failed");
-                        let cookie = self.x_connection.send_request(&x::GetProperty {
-                            delete: false,
-                            window: ev.window(),
-                            property: self.atoms.net_wm_state,
-                            r#type: x::ATOM_ATOM,
-                            long_offset: 0,
-                            long_length: reply.bytes_after(),
-                        });
-                        let reply = self
-                            .x_connection
-                            .wait_for_reply(cookie)
-                            .expect("Connection to the X server failed");
-                        let mut is_fullscreen = false;
-                        for r in reply.value::<x::Atom>().iter() {
-                            if r.resource_id() == 358 {
-                                is_fullscreen = true;
-                            }
-                        }
-
-                        self.toggle_focus(ev.window(), true);
-                        if is_fullscreen {
-                            self.toggle_ool();
-                            self.x_connection
-                                .send_request(&x::ChangeProperty::<x::Atom> {
-                                    mode: x::PropMode::Replace,
-                                    window: ev.window(),
-                                    property: self.atoms.net_wm_state,
-                                    r#type: x::ATOM_ATOM,
-                                    data: &[],
-                                });
-                        } else {
-                            self.fullscreen();
+                    self.client_message(ev);
+                }
+                x::Event::PropertyNotify(ev) => {
+                    if (ev.atom() == self.atoms.net_wm_strut
+                        || ev.atom() == self.atoms.net_wm_strut_partial)
+                        && self.is_dock(ev.window())
+                    {
+                        self.update_struts();
+                    }
+                    let title = ev.atom() == x::ATOM_WM_NAME || ev.atom() == self.atoms.net_wm_name;
+                    if title || ev.atom() == x::ATOM_WM_CLASS {
+                        self.apply_late_rules(ev.window(), title);
+                    }
+                    if title
+                        && self.has_subscribers()
+                        && self.window_location(ev.window()).is_some()
+                    {
+                        if let Some(title) = self.get_title(ev.window()) {
+                            self.emit(Event::Title {
+                                window: ev.window().resource_id(),
+                                title,
+                            });
                         }
-                        self.x_connection.flush().ok();
                     }
                 }
                 _ => {}
@@ -980,75 +1915,165 @@ impl Lapin {
         &mut self.current_screen_mut().workspaces[wk]
     }
 
-    fn current_layout<'a>(&'a self) -> &'a Box<dyn layouts::Layout> {
-        let layout = self.current_workspace().layout;
-        &self.config.layouts[layout]
-    }
-
-    fn workspace_windows<'a>(&'a self) -> std::slice::Iter<'a, x::Window> {
-        self.current_workspace().windows.iter()
+    fn current_layout(&self) -> &dyn layouts::Layout {
+        self.current_workspace().current_layout()
     }
 
     /*
-     * The following functions are the most terrible code you'll ever see in your fucking life.
-     * Their only goal is to actually get the fucking window title and classes. Unfortunatelly, it
-     * looks like xcb was designed to don't allow you to do that. Try getting them to work without
-     * all these stupid ugly workarounds and you'll see. Good luck.
-     *
-     * Also, it simply doesn't work with the title ;). Not my falt btw.
+     * The following functions read window properties. Titles are usually
+     * UTF8_STRING and not STRING, so they need their own function.
      */
 
-    fn get_string_property(&self, window: x::Window, property: x::Atom) -> Option<String> {
+    /// Returns the raw value of a property of 8 bits and its type. Any type
+    /// is accepted if `r#type` is `x::ATOM_ANY`.
+    fn get_bytes_property(
+        &self,
+        window: x::Window,
+        property: x::Atom,
+        r#type: x::Atom,
+    ) -> Option<(Vec<u8>, x::Atom)> {
         let cookie = self.x_connection.send_request(&x::GetProperty {
             delete: false,
             window,
             property,
-            r#type: x::ATOM_STRING,
+            r#type,
             long_offset: 0,
-            long_length: 0,
+            long_length: u32::MAX / 4,
         });
-        let reply = self.x_connection.wait_for_reply(cookie);
-        let reply = if reply.is_err() {
+        let reply = self.x_connection.wait_for_reply(cookie).ok()?;
+        if reply.r#type() == x::ATOM_NONE || reply.format() != 8 {
             return None;
-        } else {
-            reply.unwrap()
-        };
+        }
+        Some((reply.value::<u8>().to_vec(), reply.r#type()))
+    }
+
+    fn get_string_property(&self, window: x::Window, property: x::Atom) -> Option<String> {
+        let (mut value, _) = self.get_bytes_property(window, property, x::ATOM_STRING)?;
+        if value.last() == Some(&0) {
+            value.pop();
+        }
+        String::from_utf8(value).ok()
+    }
+
+    fn get_cardinal_property(&self, window: x::Window, property: x::Atom) -> Option<u32> {
         let cookie = self.x_connection.send_request(&x::GetProperty {
             delete: false,
             window,
             property,
-            r#type: x::ATOM_STRING,
+            r#type: x::ATOM_CARDINAL,
             long_offset: 0,
-            long_length: reply.bytes_after(),
+            long_length: 1,
         });
-        let reply = self.x_connection.wait_for_reply(cookie);
-        let reply = if reply.is_err() {
+        let reply = self.x_connection.wait_for_reply(cookie).ok()?;
+        if reply.r#type() != x::ATOM_CARDINAL {
             return None;
-        } else {
-            reply.unwrap()
-        };
-        let mut replied_value = reply.value().to_vec();
-        replied_value.pop();
-        let prop = if let Ok(prop) = String::from_utf8(replied_value) {
-            prop
-        } else {
+        }
+        reply.value::<u32>().first().copied()
+    }
+
+    /// Returns the window a window is transient for, from
+    /// `WM_TRANSIENT_FOR`.
+    fn get_transient_for(&self, window: x::Window) -> Option<x::Window> {
+        let cookie = self.x_connection.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: x::ATOM_WM_TRANSIENT_FOR,
+            r#type: x::ATOM_WINDOW,
+            long_offset: 0,
+            long_length: 1,
+        });
+        let reply = self.x_connection.wait_for_reply(cookie).ok()?;
+        if reply.r#type() != x::ATOM_WINDOW {
             return None;
-        };
-        Some(prop)
+        }
+        reply
+            .value::<x::Window>()
+            .first()
+            .copied()
+            .filter(|parent| !parent.is_none())
+    }
+
+    /// Returns the types of a window, from `_NET_WM_WINDOW_TYPE`, in order
+    /// of preference. Unknown types are skipped.
+    fn get_window_types(&self, window: x::Window) -> Vec<WindowType> {
+        let types = [
+            (self.atoms.net_wm_window_type_desktop, WindowType::Desktop),
+            (self.atoms.net_wm_window_type_dock, WindowType::Dock),
+            (self.atoms.net_wm_window_type_toolbar, WindowType::Toolbar),
+            (self.atoms.net_wm_window_type_menu, WindowType::Menu),
+            (self.atoms.net_wm_window_type_utility, WindowType::Utility),
+            (self.atoms.net_wm_window_type_splash, WindowType::Splash),
+            (self.atoms.net_wm_window_type_dialog, WindowType::Dialog),
+            (
+                self.atoms.net_wm_window_type_dropdown_menu,
+                WindowType::DropdownMenu,
+            ),
+            (
+                self.atoms.net_wm_window_type_popup_menu,
+                WindowType::PopupMenu,
+            ),
+            (self.atoms.net_wm_window_type_tooltip, WindowType::Tooltip),
+            (
+                self.atoms.net_wm_window_type_notification,
+                WindowType::Notification,
+            ),
+            (self.atoms.net_wm_window_type_combo, WindowType::Combo),
+            (self.atoms.net_wm_window_type_dnd, WindowType::Dnd),
+            (self.atoms.net_wm_window_type_normal, WindowType::Normal),
+        ];
+        self.get_atom_list_property(window, self.atoms.net_wm_window_type)
+            .into_iter()
+            .filter_map(|atom| {
+                types
+                    .iter()
+                    .find(|(type_atom, _)| *type_atom == atom)
+                    .map(|(_, window_type)| *window_type)
+            })
+            .collect()
     }
 
+    fn get_utf8_property(&self, window: x::Window, property: x::Atom) -> Option<String> {
+        let (value, _) = self.get_bytes_property(window, property, self.atoms.utf8_string)?;
+        String::from_utf8(value).ok()
+    }
+
+    /// Returns the title of a window, from `_NET_WM_NAME` or, for old
+    /// clients, `WM_NAME`.
+    fn get_title(&self, window: x::Window) -> Option<String> {
+        if let Some(title) = self.get_utf8_property(window, self.atoms.net_wm_name) {
+            return Some(title);
+        }
+        // WM_NAME is usually Latin-1, but some clients set it as UTF-8.
+        let (value, r#type) = self.get_bytes_property(window, x::ATOM_WM_NAME, x::ATOM_ANY)?;
+        if r#type == self.atoms.utf8_string {
+            String::from_utf8(value).ok()
+        } else {
+            Some(value.into_iter().map(char::from).collect())
+        }
+    }
+
+    /// Returns the instance and the class of a window.
     fn get_class(&self, window: x::Window) -> Option<(String, String)> {
-        let (class1, class2) =
-            if let Some(class) = self.get_string_property(window, x::ATOM_WM_CLASS) {
-                let mut classes = class.split('\0');
-                (
-                    classes.next().unwrap().to_string(),
-                    classes.next().unwrap().to_string(),
-                )
-            } else {
-                return None;
-            };
+        let class = self.get_string_property(window, x::ATOM_WM_CLASS)?;
+        let mut classes = class.split('\0');
+        let instance = classes.next()?.to_string();
+        let class = classes.next()?.to_string();
+        Some((instance, class))
+    }
 
-        Some((class1, class2))
+    /// Returns the properties of a window that rules can match.
+    fn window_properties(&self, window: x::Window) -> WindowProperties {
+        let (instance, class) = self.get_class(window).unzip();
+        let pid = self.get_cardinal_property(window, self.atoms.net_wm_pid);
+        WindowProperties {
+            class,
+            instance,
+            title: self.get_title(window),
+            window_types: self.get_window_types(window),
+            role: self.get_string_property(window, self.atoms.wm_window_role),
+            transient_for: self.get_transient_for(window),
+            pid,
+            executable: pid.and_then(spawn::executable_name),
+        }
     }
 }
@@ -38,7 +38,7 @@ fn main() {
         (&[MODKEY], "n", lazy! {Lapin::spawn("chromium")}),
         (&[MODKEY], "a", lazy! {Lapin::spawn("rofi -show run")}),
         // kill focus
-        (&[MODKEY], "w", lazy! {wm, wm.killfocused()}),
+        (&[MODKEY], "w", lazy! {wm, wm.close_focused()}),
         // change focus
         (&[MODKEY], "j", lazy! {wm, wm.nextwin()}),
         (&[MODKEY], "k", lazy! {wm, wm.prevwin()}),
@@ -53,7 +53,7 @@ fn main() {
         // toggle ool
         (&[MODKEY, "Shift"], "t", lazy! {wm, wm.toggle_ool()}),
         // fullscreen
-        (&[MODKEY, "Shift"], "f", lazy! {wm, wm.fullscreen()}),
+        (&[MODKEY, "Shift"], "f", lazy! {wm, wm.toggle_fullscreen()}),
         // change focused screen (monitor)
         (&[MODKEY], "y", lazy! {wm, wm.prev_screen()}),
         (&[MODKEY], "u", lazy! {wm, wm.next_screen()}),
@@ -1,53 +1,442 @@
 //! Rule system for Lapin.
 
-/// A window property. Currently supports only the class. Title
-/// support is planned.
-#[derive(Debug, PartialEq)]
+use regex::Regex;
+use xcb::x;
+
+/// How to match the value of a property.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    /// The value is exactly the string.
+    Exact(String),
+    /// The value is the string, ignoring case.
+    CaseInsensitive(String),
+    /// The value contains the string.
+    Contains(String),
+    /// The value starts with the string.
+    Prefix(String),
+    /// The value matches a regular expression. Glob patterns are
+    /// translated to one.
+    Regex(Regex),
+}
+
+impl Matcher {
+    /// Creates a matcher for a glob pattern, where `*` matches any
+    /// sequence of characters and `?` matches any single character. The
+    /// whole value must match.
+    ///
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::rules::Matcher;
+    /// let matcher = Matcher::glob("Firefox*");
+    /// assert!(matcher.matches("Firefox Developer Edition"));
+    /// assert!(!matcher.matches("firefox"));
+    /// ```
+    pub fn glob(pattern: &str) -> Self {
+        let mut regex = String::from("^");
+        for ch in pattern.chars() {
+            match ch {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                ch => regex.push_str(&regex::escape(&ch.to_string())),
+            }
+        }
+        regex.push('$');
+        Self::regex(&regex)
+    }
+
+    /// Creates a matcher for a regular expression, which may match any
+    /// part of the value. Use `^` and `$` to match the whole value.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the regular expression is invalid.
+    pub fn regex(pattern: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(regex) => Matcher::Regex(regex),
+            Err(e) => panic!("Invalid regular expression {pattern}: {e}"),
+        }
+    }
+
+    /// Checks if a value matches.
+    pub fn matches(&self, value: &str) -> bool {
+        match self {
+            Matcher::Exact(s) => value == s,
+            Matcher::CaseInsensitive(s) => value.to_lowercase() == s.to_lowercase(),
+            Matcher::Contains(s) => value.contains(s.as_str()),
+            Matcher::Prefix(s) => value.starts_with(s.as_str()),
+            Matcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+impl PartialEq for Matcher {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Matcher::Exact(a), Matcher::Exact(b))
+            | (Matcher::CaseInsensitive(a), Matcher::CaseInsensitive(b))
+            | (Matcher::Contains(a), Matcher::Contains(b))
+            | (Matcher::Prefix(a), Matcher::Prefix(b)) => a == b,
+            (Matcher::Regex(a), Matcher::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl From<&str> for Matcher {
+    fn from(s: &str) -> Self {
+        Matcher::Exact(s.to_string())
+    }
+}
+
+/// A condition on the properties of a window.
+#[derive(Debug, Clone, PartialEq)]
 pub enum Property {
-    Class(String),
-    // this is because someday it'll have support to the title
+    /// The class or the instance, i.e., either part of `WM_CLASS`.
+    Class(Matcher),
+    /// Only the instance, i.e., the first part of `WM_CLASS`.
+    Instance(Matcher),
+    /// The title, from `_NET_WM_NAME` or `WM_NAME`.
+    Title(Matcher),
+    /// The window has the type in `_NET_WM_WINDOW_TYPE`.
+    WindowType(WindowType),
+    /// The role, from `WM_WINDOW_ROLE`.
+    Role(Matcher),
+    /// The window is transient for another one, i.e., has
+    /// `WM_TRANSIENT_FOR`, like most dialogs.
+    Transient,
+    /// The PID of the client, from `_NET_WM_PID`.
+    Pid(u32),
+    /// The name of the executable of the client, found with its PID.
+    Executable(Matcher),
+    /// All the conditions are true.
+    All(Vec<Property>),
+    /// Any of the conditions is true.
+    Any(Vec<Property>),
+    /// The condition is false.
+    Not(Box<Property>),
+}
+
+impl Property {
+    /// Checks if the properties of a window satisfy the condition. A
+    /// window without the property never matches it.
+    pub fn matches(&self, window: &WindowProperties) -> bool {
+        let check = |value: &Option<String>, matcher: &Matcher| {
+            value.as_deref().is_some_and(|value| matcher.matches(value))
+        };
+        match self {
+            Property::Class(matcher) => {
+                check(&window.class, matcher) || check(&window.instance, matcher)
+            }
+            Property::Instance(matcher) => check(&window.instance, matcher),
+            Property::Title(matcher) => check(&window.title, matcher),
+            Property::WindowType(window_type) => window.window_types.contains(window_type),
+            Property::Role(matcher) => check(&window.role, matcher),
+            Property::Transient => window.transient_for.is_some(),
+            Property::Pid(pid) => window.pid == Some(*pid),
+            Property::Executable(matcher) => check(&window.executable, matcher),
+            Property::All(properties) => properties.iter().all(|p| p.matches(window)),
+            Property::Any(properties) => properties.iter().any(|p| p.matches(window)),
+            Property::Not(property) => !property.matches(window),
+        }
+    }
+
+    /// Checks if the condition depends on the title of the window.
+    pub fn uses_title(&self) -> bool {
+        self.uses(&|property| matches!(property, Property::Title(_)))
+    }
+
+    /// Checks if the condition depends on `WM_CLASS`.
+    pub fn uses_class(&self) -> bool {
+        self.uses(&|property| matches!(property, Property::Class(_) | Property::Instance(_)))
+    }
+
+    fn uses(&self, leaf: &dyn Fn(&Property) -> bool) -> bool {
+        match self {
+            Property::All(properties) | Property::Any(properties) => {
+                properties.iter().any(|p| p.uses(leaf))
+            }
+            Property::Not(property) => property.uses(leaf),
+            property => leaf(property),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+/// A window type from `_NET_WM_WINDOW_TYPE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WindowType {
+    Desktop,
+    Dock,
+    Toolbar,
+    Menu,
+    Utility,
+    Splash,
+    Dialog,
+    DropdownMenu,
+    PopupMenu,
+    Tooltip,
+    Notification,
+    Combo,
+    Dnd,
+    Normal,
+}
+
+/// The properties of a window that rules can match, as read when the
+/// window is mapped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowProperties {
+    pub class: Option<String>,
+    pub instance: Option<String>,
+    pub title: Option<String>,
+    /// Types the window has, in order of preference.
+    pub window_types: Vec<WindowType>,
+    pub role: Option<String>,
+    /// The window this one is transient for.
+    pub transient_for: Option<x::Window>,
+    pub pid: Option<u32>,
+    /// Name of the executable of the client.
+    pub executable: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 /// What to apply to the window.
 pub enum Apply {
+    /// Places the window in a workspace.
     Workspace(usize),
+    /// Places the window in a screen, in its current workspace unless
+    /// `Workspace` is also applied.
+    Screen(usize),
     Fullscreen,
     Float,
+    /// Changes the layout of the workspace the window is placed in to the
+    /// one with the name.
+    Layout(&'static str),
+    /// Moves and resizes the window, if it's floating.
+    Geometry(Geometry),
+    /// Centers the window in its screen, or over its parent if it's
+    /// transient. Only for floating windows.
+    Center,
+    /// Border width of the window, instead of the one of the layout.
+    BorderWidth(u32),
+    /// Border color of the window when not focused, in the form ARGB.
+    BorderColor(u32),
+    /// Maps the window without giving it the focus.
+    NoFocus,
+    /// Shows the workspace the window is placed in.
+    SwitchToWorkspace,
+    /// Makes the window sticky, i.e., it follows the workspace changes of
+    /// its screen.
+    Sticky,
 }
 
-#[derive(Debug, PartialEq)]
+/// A geometry for `Apply::Geometry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+    /// If the position is relative to the screen the window is placed in,
+    /// instead of the root window.
+    pub relative: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 /// A rule to apply to a window on spawn.
 pub struct Rule {
-    /// A window property. Currently supports only the class. Title
-    /// support is planned.
+    /// A window property.
     pub property: Property,
     /// What to apply to the window.
-    pub apply: Apply,
+    pub apply: Vec<Apply>,
+    /// If the rule is checked again when the class or the title of the
+    /// window changes after it's mapped, for programs that only set them
+    /// later. A late rule is applied at most once per window.
+    pub late: bool,
 }
 
 impl Rule {
     /// Creates a new rule. Not recommended, use the macro `rule!` instead.
-    pub fn new(property: Property, apply: Apply) -> Self {
-        Rule { property, apply }
+    /// Accepts a single action or a list of them.
+    pub fn new(property: Property, apply: impl Into<Vec<Apply>>) -> Self {
+        Rule {
+            property,
+            apply: apply.into(),
+            late: false,
+        }
     }
+
+    /// Makes the rule a late rule.
+    pub fn late(mut self) -> Self {
+        self.late = true;
+        self
+    }
+}
+
+impl From<Apply> for Vec<Apply> {
+    fn from(apply: Apply) -> Self {
+        vec![apply]
+    }
+}
+
+/// Where and how to place a new window, decided by the rules. Fields are
+/// `None` or `false` when no rule said anything about them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Placement {
+    pub screen: Option<usize>,
+    pub workspace: Option<usize>,
+    pub fullscreen: bool,
+    pub float: bool,
+    pub layout: Option<&'static str>,
+    pub geometry: Option<Geometry>,
+    pub center: bool,
+    pub border_width: Option<u32>,
+    pub border_color: Option<u32>,
+    pub no_focus: bool,
+    pub switch_to_workspace: bool,
+    pub sticky: bool,
 }
 
-/// Macro to easily create rules
+impl Placement {
+    /// Applies an action. Later actions override earlier ones.
+    pub fn apply(&mut self, apply: &Apply) {
+        match apply {
+            Apply::Workspace(n) => self.workspace = Some(*n),
+            Apply::Screen(n) => self.screen = Some(*n),
+            Apply::Fullscreen => self.fullscreen = true,
+            Apply::Float => self.float = true,
+            Apply::Layout(name) => self.layout = Some(name),
+            Apply::Geometry(geometry) => self.geometry = Some(*geometry),
+            Apply::Center => self.center = true,
+            Apply::BorderWidth(width) => self.border_width = Some(*width),
+            Apply::BorderColor(color) => self.border_color = Some(*color),
+            Apply::NoFocus => self.no_focus = true,
+            Apply::SwitchToWorkspace => self.switch_to_workspace = true,
+            Apply::Sticky => self.sticky = true,
+        }
+    }
+}
+
+/// Macro to easily create rules. A rule matches a property (`class`,
+/// `instance`, `title`, `role` or `executable`) with a string. By default
+/// the value must be exactly the string, but the string may be preceded by
+/// how to match it: `nocase` to ignore case, `contains`, `prefix`, `glob`
+/// or `regex`. There are also `window_type` followed by a `WindowType`,
+/// `transient` and `pid` followed by a number. Properties may be negated
+/// with `not`, and combined with `all` and `any`, with each property inside
+/// parenthesis. Rules starting with `late` are late rules (see
+/// `Rule::late`). Note that `class` matches either part of `WM_CLASS`,
+/// while `instance` only matches the first one.
+///
+/// # Example
 /// ```
 /// use le_petit_lapin::*;
 /// use le_petit_lapin::rules::*;
 /// rule!(class "Gimp" => Apply::Fullscreen);
 /// rule!(class "QjackCtl" => Apply::Float);
+/// rule!(instance "pavucontrol" => Apply::Float);
+/// rule!(title "Picture-in-Picture" => Apply::Float);
+/// rule!(class nocase "firefox" => Apply::Workspace(1));
+/// rule!(title contains "Discord" => Apply::Workspace(8));
+/// rule!(class glob "jetbrains-*" => Apply::Workspace(2));
+/// rule!(title regex "^Steam( - .*)?$" => Apply::Workspace(4));
+/// rule!(not class prefix "Alacritty" => Apply::Float);
+/// // Steam windows but the main one.
+/// rule!(all [(class "Steam"), (not title "Steam")] => Apply::Float);
+/// rule!(any [(class "mpv"), (class "vlc")] => Apply::Fullscreen);
+/// rule!(window_type Dialog => Apply::Float);
+/// rule!(transient => Apply::Float);
+/// rule!(all [(class "firefox"), (role "PictureInPicture")] => Apply::Float);
+/// rule!(executable "mpv" => Apply::Workspace(5));
+/// // many actions.
+/// rule!(class "Pavucontrol" => [Apply::Float, Apply::Center, Apply::NoFocus]);
+/// // Spotify sets its class only after mapping the window.
+/// rule!(late class "Spotify" => Apply::Workspace(8));
 /// ```
 #[macro_export]
 macro_rules! rule {
-    (class $name:literal => $apply:expr) => {
-        Rule {
-            property: Property::Class(String::from($name)),
-            apply: $apply,
-        }
-    }; // (title $name:literal => $apply:expr) => {
-       //     Rule { property: Property::Title(String::from($name)), apply: $apply }
-       // };
+    (late $($rule:tt)+) => {
+        $crate::rule!($($rule)+).late()
+    };
+    ($($word:ident)+ $value:literal => $apply:expr) => {
+        $crate::rules::Rule::new($crate::property!($($word)+ $value), $apply)
+    };
+    ($($word:ident)+ => $apply:expr) => {
+        $crate::rules::Rule::new($crate::property!($($word)+), $apply)
+    };
+    ($($word:ident)+ [$($group:tt)*] => $apply:expr) => {
+        $crate::rules::Rule::new($crate::property!($($word)+ [$($group)*]), $apply)
+    };
+}
+
+/// Creates a `rules::Property` with the syntax of `rule!`.
+///
+/// # Example
+/// ```
+/// use le_petit_lapin::*;
+/// use le_petit_lapin::rules::*;
+/// let property = property!(all [(class "Steam"), (not title "Steam")]);
+/// let window = WindowProperties {
+///     class: Some(String::from("Steam")),
+///     title: Some(String::from("Friends List")),
+///     ..Default::default()
+/// };
+/// assert!(property.matches(&window));
+/// ```
+#[macro_export]
+macro_rules! property {
+    (not $($rest:tt)+) => {
+        $crate::rules::Property::Not(Box::new($crate::property!($($rest)+)))
+    };
+    (all [$(($($property:tt)+)),* $(,)?]) => {
+        $crate::rules::Property::All(vec![$($crate::property!($($property)+)),*])
+    };
+    (any [$(($($property:tt)+)),* $(,)?]) => {
+        $crate::rules::Property::Any(vec![$($crate::property!($($property)+)),*])
+    };
+    (class $($matcher:tt)+) => {
+        $crate::rules::Property::Class($crate::matcher!($($matcher)+))
+    };
+    (instance $($matcher:tt)+) => {
+        $crate::rules::Property::Instance($crate::matcher!($($matcher)+))
+    };
+    (title $($matcher:tt)+) => {
+        $crate::rules::Property::Title($crate::matcher!($($matcher)+))
+    };
+    (window_type $window_type:ident) => {
+        $crate::rules::Property::WindowType($crate::rules::WindowType::$window_type)
+    };
+    (role $($matcher:tt)+) => {
+        $crate::rules::Property::Role($crate::matcher!($($matcher)+))
+    };
+    (transient) => {
+        $crate::rules::Property::Transient
+    };
+    (pid $pid:literal) => {
+        $crate::rules::Property::Pid($pid)
+    };
+    (executable $($matcher:tt)+) => {
+        $crate::rules::Property::Executable($crate::matcher!($($matcher)+))
+    };
+}
+
+/// Creates a `rules::Matcher` with the syntax of `rule!`.
+#[macro_export]
+macro_rules! matcher {
+    ($value:literal) => {
+        $crate::rules::Matcher::Exact(String::from($value))
+    };
+    (nocase $value:literal) => {
+        $crate::rules::Matcher::CaseInsensitive(String::from($value))
+    };
+    (contains $value:literal) => {
+        $crate::rules::Matcher::Contains(String::from($value))
+    };
+    (prefix $value:literal) => {
+        $crate::rules::Matcher::Prefix(String::from($value))
+    };
+    (glob $value:literal) => {
+        $crate::rules::Matcher::glob($value)
+    };
+    (regex $value:literal) => {
+        $crate::rules::Matcher::regex($value)
+    };
 }
@@ -1,7 +1,10 @@
+use crate::layouts::Layout;
 use crate::*;
+use xcb::randr;
 use xcb::x;
 
-/// A physical screen, as detected by xinerama.
+/// A physical screen, as detected by xinerama. Its settings can be changed
+/// with `Config::screens`.
 pub struct Screen {
     pub workspaces: Vec<Workspace>,
     pub current_wk: usize,
@@ -9,6 +12,11 @@ pub struct Screen {
     pub height: u16,
     pub x: i16,
     pub y: i16,
+    /// Name of the RandR output showing the screen, if known.
+    pub output: Option<String>,
+    /// Space reserved by docks, in the format (top, right, bottom, left).
+    /// Updated automatically when docks change.
+    pub reserved_space: (u16, u16, u16, u16),
 }
 
 impl Screen {
@@ -16,8 +24,12 @@ impl Screen {
     /// Only use it manually if you know what you're doing.
     pub fn new(lapin: &Lapin, width: u16, height: u16, x: i16, y: i16) -> Self {
         let mut workspaces = Vec::with_capacity(lapin.config.workspaces.len());
-        for workspace in lapin.config.workspaces {
-            workspaces.push(Workspace::new(workspace));
+        for (k, workspace) in lapin.config.workspaces.iter().enumerate() {
+            let mut workspace = Workspace::new(workspace, lapin.workspace_layouts(k));
+            if let Some(layout) = lapin.workspace_default_layout(k, &workspace) {
+                workspace.layout = layout;
+            }
+            workspaces.push(workspace);
         }
 
         lapin.x_connection.flush().ok();
@@ -29,6 +41,8 @@ impl Screen {
             height,
             x,
             y,
+            output: None,
+            reserved_space: (0, 0, 0, 0),
         }
     }
 
@@ -45,6 +59,10 @@ pub struct Workspace {
     pub ool_focus: bool,
     pub windows: Vec<x::Window>,
     pub ool_windows: Vec<x::Window>,
+    /// The layouts of the workspace, owned by it, except for the ones
+    /// shared by every workspace (see `Layout::boxed_clone()`).
+    pub layouts: Vec<Box<dyn Layout>>,
+    /// Index of the layout in use.
     pub layout: usize,
     pub respect_reserved_space: bool,
 }
@@ -52,16 +70,234 @@ pub struct Workspace {
 impl Workspace {
     /// Creates a new workspace. The `Lapin::init()` function should create then
     /// based in the config struct. Only create then manually if you know what
-    /// you're doing.
-    pub fn new(name: &'static str) -> Self {
+    /// you're doing. There must be at least one layout.
+    ///
+    /// Older versions took only the name, as the layouts were the same for
+    /// every workspace.
+    pub fn new(name: &'static str, layouts: Vec<Box<dyn Layout>>) -> Self {
         Workspace {
             name,
             focused: None,
             ool_focus: false,
             windows: Vec::new(),
             ool_windows: Vec::new(),
+            layouts,
             layout: 0,
             respect_reserved_space: true,
         }
     }
+
+    /// Gets the layout in use.
+    pub fn current_layout(&self) -> &dyn Layout {
+        self.layouts[self.layout].as_ref()
+    }
+
+    /// Returns the index of a layout of the workspace by its name.
+    pub fn layout_position(&self, name: &str) -> Option<usize> {
+        self.layouts.iter().position(|layout| layout.name() == name)
+    }
+
+    /// Inserts a window at the top of the stack (or of the ool stack),
+    /// keeping the focus on the window it was, or focusing the new one if
+    /// nothing was focused.
+    pub fn insert_window(&mut self, window: x::Window, ool: bool) {
+        self.insert_window_at(0, window, ool);
+    }
+
+    /// Same as `insert_window`, but inserts the window at a given position
+    /// of the stack.
+    pub fn insert_window_at(&mut self, index: usize, window: x::Window, ool: bool) {
+        if ool {
+            self.ool_windows.insert(index, window);
+        } else {
+            self.windows.insert(index, window);
+        }
+        match self.focused {
+            None => {
+                self.focused = Some(index);
+                self.ool_focus = ool;
+            }
+            Some(f) if self.ool_focus == ool && f >= index => self.focused = Some(f + 1),
+            _ => {}
+        }
+    }
+
+    /// Removes a window from the workspace, keeping the focus index valid.
+    /// Returns if the window was an ool window, or `None` if the window is
+    /// not in the workspace.
+    pub fn remove_window(&mut self, window: x::Window) -> Option<bool> {
+        let (w, ool) = if let Some(w) = self.windows.iter().position(|win| *win == window) {
+            self.windows.remove(w);
+            (w, false)
+        } else if let Some(w) = self.ool_windows.iter().position(|win| *win == window) {
+            self.ool_windows.remove(w);
+            (w, true)
+        } else {
+            return None;
+        };
+
+        if let Some(f) = self.focused {
+            if self.ool_focus == ool && f > w {
+                self.focused = Some(f - 1);
+            }
+        }
+        if let Some(f) = self.focused {
+            let len = if self.ool_focus {
+                self.ool_windows.len()
+            } else {
+                self.windows.len()
+            };
+            if f >= len {
+                self.focused = if len > 0 {
+                    Some(len - 1)
+                } else if !self.windows.is_empty() {
+                    self.ool_focus = false;
+                    Some(0)
+                } else if !self.ool_windows.is_empty() {
+                    self.ool_focus = true;
+                    Some(0)
+                } else {
+                    None
+                };
+            }
+        }
+        Some(ool)
+    }
+}
+
+impl Lapin {
+    /// Finds the RandR output showing each screen, by matching their
+    /// geometries. Screens without a match keep `output` as `None`.
+    pub(crate) fn name_screens(&mut self) {
+        let cookie = self
+            .x_connection
+            .send_request(&randr::GetScreenResourcesCurrent { window: self.root });
+        let Ok(resources) = self.x_connection.wait_for_reply(cookie) else {
+            return;
+        };
+        for output in resources.outputs() {
+            let cookie = self.x_connection.send_request(&randr::GetOutputInfo {
+                output: *output,
+                config_timestamp: resources.config_timestamp(),
+            });
+            let Ok(info) = self.x_connection.wait_for_reply(cookie) else {
+                continue;
+            };
+            // disconnected or disabled.
+            if info.crtc().is_none() {
+                continue;
+            }
+            let cookie = self.x_connection.send_request(&randr::GetCrtcInfo {
+                crtc: info.crtc(),
+                config_timestamp: resources.config_timestamp(),
+            });
+            let Ok(crtc) = self.x_connection.wait_for_reply(cookie) else {
+                continue;
+            };
+            let name = String::from_utf8_lossy(info.name()).to_string();
+            for screen in self.screens.iter_mut() {
+                if screen.output.is_none()
+                    && (screen.x, screen.y, screen.width, screen.height)
+                        == (crtc.x(), crtc.y(), crtc.width(), crtc.height())
+                {
+                    screen.output = Some(name);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Returns the settings of a screen in `Config::screens`, if any.
+    pub(crate) fn screen_config(&self, s: usize) -> Option<&ScreenConfig> {
+        let output = self.screens[s].output.as_deref();
+        self.config
+            .screens
+            .iter()
+            .find(|config| match config.monitor {
+                Monitor::Index(index) => index == s,
+                Monitor::Output(name) => output == Some(name),
+            })
+    }
+
+    /// Returns copies of the layouts available in a workspace: the ones
+    /// in its `WorkspaceConfig`, or every one in `Config::layouts`.
+    pub(crate) fn workspace_layouts(&self, k: usize) -> Vec<Box<dyn Layout>> {
+        let names = self
+            .workspace_config(k)
+            .map(|config| &config.layouts[..])
+            .unwrap_or_default();
+        let layouts: Vec<Box<dyn Layout>> = names
+            .iter()
+            .filter_map(|name| {
+                self.config
+                    .layouts
+                    .iter()
+                    .find(|layout| layout.name() == *name)
+            })
+            .filter_map(|layout| layout.boxed_clone())
+            .collect();
+        // a typo in the names shouldn't leave the workspace without
+        // layouts.
+        if layouts.is_empty() {
+            self.config
+                .layouts
+                .iter()
+                .filter_map(|layout| layout.boxed_clone())
+                .collect()
+        } else {
+            layouts
+        }
+    }
+
+    /// Returns the index of the layout a workspace starts with, if its
+    /// `WorkspaceConfig` sets it.
+    pub(crate) fn workspace_default_layout(
+        &self,
+        k: usize,
+        workspace: &Workspace,
+    ) -> Option<usize> {
+        self.workspace_config(k)
+            .and_then(|config| config.default_layout)
+            .and_then(|name| workspace.layout_position(name))
+    }
+
+    /// Returns the settings of a workspace in `Config::workspace_configs`,
+    /// if any.
+    fn workspace_config(&self, k: usize) -> Option<&WorkspaceConfig> {
+        self.config
+            .workspace_configs
+            .iter()
+            .find(|config| config.workspace == k)
+    }
+
+    /// Sets the starting workspace and the layouts of the screens as their
+    /// settings ask.
+    pub(crate) fn apply_screen_configs(&mut self) {
+        for s in 0..self.screens.len() {
+            let Some(config) = self.screen_config(s) else {
+                continue;
+            };
+            let workspace = config
+                .workspace
+                .filter(|k| *k < self.screens[s].workspaces.len());
+            let layout = config.layout;
+            if let Some(k) = workspace {
+                self.screens[s].current_wk = k;
+            }
+            let Some(name) = layout else {
+                continue;
+            };
+            for k in 0..self.screens[s].workspaces.len() {
+                // the default layout of a workspace wins over the one of
+                // the screen.
+                let workspace = &self.screens[s].workspaces[k];
+                if self.workspace_default_layout(k, workspace).is_some() {
+                    continue;
+                }
+                if let Some(l) = workspace.layout_position(name) {
+                    self.screens[s].workspaces[k].layout = l;
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,200 @@
+//! Size hints of windows, as set by clients in `WM_NORMAL_HINTS`.
+//!
+//! Clients use them to tell the window manager how they may be resized:
+//! minimum and maximum sizes, resize increments (e.g. the size of a cell in
+//! a terminal), a base size and aspect ratios. Floating windows always
+//! honor them. Tiled windows honor them if `Layout::size_hints()` says so.
+
+use crate::layouts::Rect;
+use xcb::x;
+use xcb::Connection;
+
+const P_MIN_SIZE: u32 = 1 << 4;
+const P_MAX_SIZE: u32 = 1 << 5;
+const P_RESIZE_INC: u32 = 1 << 6;
+const P_ASPECT: u32 = 1 << 7;
+const P_BASE_SIZE: u32 = 1 << 8;
+
+/// The size hints of a window. Sizes don't include the border.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeHints {
+    pub min: Option<(u32, u32)>,
+    pub max: Option<(u32, u32)>,
+    pub base: Option<(u32, u32)>,
+    /// Width and height increments.
+    pub increment: Option<(u32, u32)>,
+    /// Minimum and maximum aspect ratios, as (width, height).
+    pub aspect: Option<((u32, u32), (u32, u32))>,
+}
+
+impl SizeHints {
+    /// Reads the size hints of a window. Returns `None` if it has none.
+    pub fn get(con: &Connection, window: x::Window) -> Option<Self> {
+        Self::wait(con, Self::request(con, window))
+    }
+
+    /// Asks for the size hints of a window without waiting, so the hints
+    /// of many windows can be read in a single round trip with `wait()`.
+    pub fn request(con: &Connection, window: x::Window) -> x::GetPropertyCookie {
+        con.send_request(&x::GetProperty {
+            delete: false,
+            window,
+            property: x::ATOM_WM_NORMAL_HINTS,
+            r#type: x::ATOM_WM_SIZE_HINTS,
+            long_offset: 0,
+            long_length: 18,
+        })
+    }
+
+    /// Waits for the size hints asked with `request()`. Returns `None` if
+    /// the window has none.
+    pub fn wait(con: &Connection, cookie: x::GetPropertyCookie) -> Option<Self> {
+        let reply = con.wait_for_reply(cookie).ok()?;
+        if reply.r#type() != x::ATOM_WM_SIZE_HINTS || reply.format() != 32 {
+            return None;
+        }
+        Some(Self::from_values(reply.value()))
+    }
+
+    /// Parses the values of a `WM_SIZE_HINTS` property. Old clients may set
+    /// only the first 15 values, without the base size.
+    ///
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::size_hints::*;
+    /// // a terminal with 8x16 cells and 2 pixels of padding.
+    /// let hints = SizeHints::from_values(&[
+    ///     0x150, 0, 0, 0, 0, 20, 36, 0, 0, 8, 16, 0, 0, 0, 0, 4, 4, 0,
+    /// ]);
+    /// assert_eq!(hints.increment, Some((8, 16)));
+    /// assert_eq!(hints.constrain(803, 605), (796, 596));
+    /// ```
+    pub fn from_values(values: &[u32]) -> Self {
+        let value = |n: usize| values.get(n).copied().unwrap_or(0);
+        let pair = |n: usize| (value(n), value(n + 1));
+        let flags = value(0);
+        let mut hints = SizeHints::default();
+        if flags & P_MIN_SIZE != 0 {
+            hints.min = Some(pair(5));
+        }
+        if flags & P_MAX_SIZE != 0 {
+            hints.max = Some(pair(7)).filter(|(w, h)| *w > 0 && *h > 0);
+        }
+        if flags & P_RESIZE_INC != 0 {
+            hints.increment = Some(pair(9)).filter(|(w, h)| *w > 0 && *h > 0);
+        }
+        if flags & P_ASPECT != 0 {
+            let (min, max) = (pair(11), pair(13));
+            if min.0 > 0 && min.1 > 0 && max.0 > 0 && max.1 > 0 {
+                hints.aspect = Some((min, max));
+            }
+        }
+        if flags & P_BASE_SIZE != 0 && values.len() >= 17 {
+            hints.base = Some(pair(15));
+        }
+        hints
+    }
+
+    /// If the window can't be resized.
+    pub fn is_fixed(&self) -> bool {
+        self.min.is_some() && self.min == self.max
+    }
+
+    /// Returns the size closest to `width` and `height` that respects the
+    /// hints, following the ICCCM. It may be bigger than the requested size
+    /// if the minimum size asks for it.
+    pub fn constrain(&self, width: u32, height: u32) -> (u32, u32) {
+        // the ICCCM says each of them falls back to the other.
+        let base = self.base.or(self.min).unwrap_or((0, 0));
+        let min = self.min.or(self.base).unwrap_or((1, 1));
+        let (mut width, mut height) = (width.max(min.0) as f64, height.max(min.1) as f64);
+
+        if let Some(((min_w, min_h), (max_w, max_h))) = self.aspect {
+            // aspect ratios don't count the base size, unless it's also the
+            // minimum size.
+            let (base_w, base_h) = if self.base.is_some() && self.base != self.min {
+                (base.0 as f64, base.1 as f64)
+            } else {
+                (0.0, 0.0)
+            };
+            let (w, h) = ((width - base_w).max(1.0), (height - base_h).max(1.0));
+            let (min_ratio, max_ratio) = (min_w as f64 / min_h as f64, max_w as f64 / max_h as f64);
+            if w / h > max_ratio {
+                width = base_w + (h * max_ratio).round();
+            } else if w / h < min_ratio {
+                height = base_h + (w / min_ratio).round();
+            }
+        }
+
+        let (mut width, mut height) = (width as u32, height as u32);
+        if let Some((inc_w, inc_h)) = self.increment {
+            width -= width.saturating_sub(base.0) % inc_w;
+            height -= height.saturating_sub(base.1) % inc_h;
+        }
+        width = width.max(min.0).max(1);
+        height = height.max(min.1).max(1);
+        if let Some((max_w, max_h)) = self.max {
+            width = width.min(max_w);
+            height = height.min(max_h);
+        }
+        (width, height)
+    }
+
+    /// Returns the geometry of a window that follows the hints, centered
+    /// in a cell. The window may still overflow the cell to the right and
+    /// bottom if its minimum size asks for it.
+    ///
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::layouts::Rect;
+    /// use le_petit_lapin::size_hints::*;
+    /// let mut hints = SizeHints::default();
+    /// hints.max = Some((300, 200));
+    /// let fit = hints.fit(Rect::new(100, 0, 500, 400));
+    /// assert_eq!(fit, Rect::new(200, 100, 300, 200));
+    /// ```
+    pub fn fit(&self, cell: Rect) -> Rect {
+        let (width, height) = self.constrain(cell.width, cell.height);
+        Rect {
+            x: cell.x + (cell.width.saturating_sub(width) / 2) as i32,
+            y: cell.y + (cell.height.saturating_sub(height) / 2) as i32,
+            width,
+            height,
+        }
+    }
+
+    /// Sends a request to place a window in a cell of a layout, with the
+    /// size not including the border. If `honor_hints` is `true` and the
+    /// window has size hints, it's resized following them and centered in
+    /// the cell.
+    pub fn configure_in_cell(
+        con: &Connection,
+        window: x::Window,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        honor_hints: bool,
+    ) {
+        let cell = Rect::new(x, y, width, height);
+        let hints = if honor_hints {
+            Self::get(con, window)
+        } else {
+            None
+        };
+        let rect = match hints {
+            Some(hints) => hints.fit(cell),
+            None => cell,
+        };
+        let list = [
+            x::ConfigWindow::X(rect.x),
+            x::ConfigWindow::Y(rect.y),
+            x::ConfigWindow::Width(rect.width),
+            x::ConfigWindow::Height(rect.height),
+        ];
+        con.send_request(&x::ConfigureWindow {
+            window,
+            value_list: &list,
+        });
+    }
+}
@@ -0,0 +1,303 @@
+//! Spawning of programs.
+//!
+//! Programs spawned with `Spawn` are reaped automatically when they exit,
+//! so they don't stay as zombies. Other children of the window manager are
+//! left alone, so waiting for them yourself (e.g. with
+//! `std::process::Command::status()`) works as usual.
+
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Maximum number of spawned programs running at once that are reaped by
+/// the SIGCHLD handler. The rest are waited for by a thread each.
+const MAX_TRACKED: usize = 256;
+/// PIDs of running programs spawned with `Spawn`, or 0 for free slots. It's
+/// read by a signal handler, so it can't be behind a lock.
+static TRACKED: [AtomicI32; MAX_TRACKED] = [const { AtomicI32::new(0) }; MAX_TRACKED];
+
+/// Programs spawned since the window manager last checked, as (PID,
+/// startup id). Only filled if `TRACK_SPAWNS` is set.
+static SPAWNED: Mutex<Vec<(u32, String)>> = Mutex::new(Vec::new());
+/// If the window manager wants to know which programs are spawned, as
+/// `Config::spawn_on_origin` asks.
+static TRACK_SPAWNS: AtomicBool = AtomicBool::new(false);
+/// Counter to make startup ids unique.
+static STARTUP_IDS: AtomicUsize = AtomicUsize::new(0);
+
+/// A program to spawn, with its arguments, environment and working
+/// directory.
+///
+/// # Example
+///
+/// ```no_run
+/// use le_petit_lapin::spawn::*;
+/// // arguments may be quoted as in a shell.
+/// Spawn::new("rofi -show \"drun\"").spawn();
+/// // shell syntax requires a shell.
+/// Spawn::shell("xrandr | grep connected > /tmp/outputs").spawn();
+/// // environment and working directory can be changed.
+/// let pid = Spawn::argv(&["make", "-j4"])
+///     .env("CC", "clang")
+///     .dir("/home/lapin/src")
+///     .spawn();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Spawn {
+    argv: Vec<String>,
+    env: Vec<(String, String)>,
+    dir: Option<String>,
+}
+
+impl Spawn {
+    /// Creates a spawn from a command line. Arguments are separated by
+    /// spaces, and may be quoted or escaped as in a shell, but it DOES NOT
+    /// run inside a shell, so there's no expansion, pipes, etc.
+    pub fn new(command: &str) -> Self {
+        Self::from_vec(split_command(command))
+    }
+
+    /// Creates a spawn from a list with the program and its arguments.
+    pub fn argv(argv: &[&str]) -> Self {
+        Self::from_vec(argv.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    /// Creates a spawn that runs the command line inside `sh`.
+    pub fn shell(command: &str) -> Self {
+        Self::argv(&["sh", "-c", command])
+    }
+
+    fn from_vec(argv: Vec<String>) -> Self {
+        Spawn {
+            argv,
+            env: Vec::new(),
+            dir: None,
+        }
+    }
+
+    /// Adds a variable to the environment of the program.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the working directory of the program. Defaults to the one of
+    /// the window manager.
+    pub fn dir(mut self, dir: &str) -> Self {
+        self.dir = Some(dir.to_string());
+        self
+    }
+
+    /// Spawns the program. Returns its PID, or `None` if it could not be
+    /// spawned.
+    pub fn spawn(&self) -> Option<u32> {
+        if !TRACK_SPAWNS.load(Ordering::Relaxed) {
+            return run(self.command()?);
+        }
+        let (pid, startup_id) = self.spawn_with_startup_id()?;
+        SPAWNED.lock().unwrap().push((pid, startup_id));
+        Some(pid)
+    }
+
+    /// Spawns the program with a startup notification id in
+    /// `DESKTOP_STARTUP_ID`, so its windows can be recognized later. Returns
+    /// the PID and the id.
+    pub(crate) fn spawn_with_startup_id(&self) -> Option<(u32, String)> {
+        let startup_id = format!(
+            "lapin-{}-{}",
+            process::id(),
+            STARTUP_IDS.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut command = self.command()?;
+        command.env("DESKTOP_STARTUP_ID", &startup_id);
+        let pid = run(command)?;
+        Some((pid, startup_id))
+    }
+
+    /// Builds the command to run the program.
+    fn command(&self) -> Option<process::Command> {
+        let (program, args) = self.argv.split_first()?;
+        let mut command = process::Command::new(program);
+        command.args(args);
+        for (key, value) in &self.env {
+            command.env(key, value);
+        }
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        Some(command)
+    }
+}
+
+/// Runs a command, reaping it when it exits. Returns its PID.
+fn run(mut command: process::Command) -> Option<u32> {
+    let child = command.spawn().ok()?;
+    let pid = child.id();
+    track_child(child);
+    Some(pid)
+}
+
+/// Splits a command line in arguments like a shell would. Single quotes
+/// keep everything literally, and inside double quotes or outside quotes
+/// a backslash escapes the next character. There's no other expansion.
+///
+/// # Example
+///
+/// ```
+/// use le_petit_lapin::spawn::split_command;
+/// assert_eq!(split_command("rofi -show \"drun\""), ["rofi", "-show", "drun"]);
+/// assert_eq!(split_command(r"echo 'a  b' c\ d ''"), ["echo", "a  b", "c d", ""]);
+/// ```
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut arg = String::new();
+    // an argument may be empty if quoted, e.g. ''.
+    let mut in_arg = false;
+    let mut chars = command.chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' => {
+                in_arg = true;
+                for ch in chars.by_ref() {
+                    if ch == '\'' {
+                        break;
+                    }
+                    arg.push(ch);
+                }
+            }
+            '"' => {
+                in_arg = true;
+                while let Some(ch) = chars.next() {
+                    match ch {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(ch) = chars.next() {
+                                arg.push(ch);
+                            }
+                        }
+                        ch => arg.push(ch),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                if let Some(ch) = chars.next() {
+                    arg.push(ch);
+                }
+            }
+            ch if ch.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            ch => {
+                in_arg = true;
+                arg.push(ch);
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+    args
+}
+
+/// Sets if `Spawn::spawn()` should give programs a startup id and record
+/// them for `take_spawned()`. Called by `Lapin::init()`.
+pub(crate) fn set_tracking(track: bool) {
+    TRACK_SPAWNS.store(track, Ordering::Relaxed);
+}
+
+/// Takes the programs spawned since the last call, as (PID, startup id).
+pub(crate) fn take_spawned() -> Vec<(u32, String)> {
+    std::mem::take(&mut *SPAWNED.lock().unwrap())
+}
+
+/// Returns the parent of a process, read from `/proc`.
+pub(crate) fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // the format is "pid (command) state ppid ...", and the command may
+    // have spaces and parenthesis.
+    let after_command = &stat[stat.rfind(')')? + 1..];
+    after_command.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Returns the name of the executable of a process, read from `/proc`.
+pub(crate) fn executable_name(pid: u32) -> Option<String> {
+    let exe = std::fs::read_link(format!("/proc/{pid}/exe")).ok();
+    match exe {
+        Some(exe) => Some(exe.file_name()?.to_string_lossy().into_owned()),
+        // we may not be allowed to follow the link, but the command name
+        // is always readable.
+        None => std::fs::read_to_string(format!("/proc/{pid}/comm"))
+            .ok()
+            .map(|comm| comm.trim_end().to_string()),
+    }
+}
+
+/// Makes sure a spawned program is reaped when it exits.
+fn track_child(mut child: process::Child) {
+    let pid = child.id() as i32;
+    let slot = TRACKED.iter().find(|slot| {
+        slot.compare_exchange(0, pid, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    });
+    match slot {
+        // it may have exited before it was tracked, and then nobody will
+        // reap it on SIGCHLD.
+        Some(slot) => reap_slot(slot),
+        None => {
+            std::thread::spawn(move || child.wait());
+        }
+    }
+}
+
+/// Reaps the program in a slot if it already exited, freeing the slot.
+fn reap_slot(slot: &AtomicI32) {
+    let pid = slot.load(Ordering::SeqCst);
+    if pid > 0 && unsafe { libc::waitpid(pid, std::ptr::null_mut(), libc::WNOHANG) } == pid {
+        slot.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Returns where `errno` is, which has a different name in each libc.
+unsafe fn errno_location() -> *mut libc::c_int {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "hurd",
+        target_os = "redox"
+    ))]
+    return libc::__errno_location();
+    #[cfg(any(target_os = "android", target_os = "openbsd", target_os = "netbsd"))]
+    return libc::__errno();
+    #[cfg(any(target_os = "freebsd", target_os = "macos", target_os = "ios"))]
+    return libc::__error();
+    #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+    return libc::___errno();
+}
+
+/// Reaps the spawned programs that already exited. It's a signal handler,
+/// so it must only do async-signal-safe stuff, and leave `errno` as it was.
+extern "C" fn reap_children(_: libc::c_int) {
+    let errno = unsafe { *errno_location() };
+    for slot in &TRACKED {
+        reap_slot(slot);
+    }
+    unsafe { *errno_location() = errno };
+}
+
+/// Reaps children on SIGCHLD. Called by `Lapin::init()`.
+pub(crate) fn setup_reaper() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = reap_children as extern "C" fn(libc::c_int) as usize;
+        action.sa_flags = libc::SA_RESTART | libc::SA_NOCLDSTOP;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(libc::SIGCHLD, &action, std::ptr::null_mut());
+    }
+    // children that exited before we were watching.
+    reap_children(0);
+}
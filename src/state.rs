@@ -0,0 +1,166 @@
+//! Snapshots of the window manager state.
+//!
+//! `Lapin::state_snapshot()` returns the whole tree of screens, workspaces
+//! and windows, which can be inspected directly or dumped as JSON with
+//! `State::to_json()`. The same JSON is returned by the `state` IPC
+//! command, so `lapinctl state` prints it.
+
+use crate::Lapin;
+use serde::Serialize;
+use xcb::x;
+use xcb::Xid;
+
+/// The state of the window manager.
+#[derive(Debug, Clone, Serialize)]
+pub struct State {
+    /// Index of the focused screen.
+    pub current_screen: usize,
+    /// Id of the focused window, if any.
+    pub focused: Option<u32>,
+    pub screens: Vec<ScreenState>,
+}
+
+/// The state of a screen.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenState {
+    /// Name of the RandR output showing the screen, if known.
+    pub output: Option<String>,
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+    /// Index of the workspace shown in the screen.
+    pub current_workspace: usize,
+    pub workspaces: Vec<WorkspaceState>,
+}
+
+/// The state of a workspace.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceState {
+    pub name: String,
+    /// Name of the layout in use.
+    pub layout: String,
+    pub respect_reserved_space: bool,
+    /// Id of the window that has the focus in the workspace, if any.
+    pub focused: Option<u32>,
+    /// Windows in the layout, followed by the ool ones.
+    pub windows: Vec<WindowState>,
+}
+
+/// The state of a window.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowState {
+    /// The X id of the window.
+    pub id: u32,
+    pub class: Option<String>,
+    pub instance: Option<String>,
+    pub title: Option<String>,
+    /// If the window is out of the layout.
+    pub ool: bool,
+    /// Geometry, if the X server could tell it.
+    pub geometry: Option<WindowGeometry>,
+}
+
+/// The geometry of a window, with the size not including the border.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WindowGeometry {
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+    pub border_width: u16,
+}
+
+impl State {
+    /// Dumps the state as JSON.
+    ///
+    /// # Example
+    /// ```
+    /// use le_petit_lapin::state::*;
+    /// let state = State {
+    ///     current_screen: 0,
+    ///     focused: None,
+    ///     screens: vec![],
+    /// };
+    /// assert_eq!(state.to_json(), r#"{"current_screen":0,"focused":null,"screens":[]}"#);
+    /// ```
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl Lapin {
+    /// Returns a snapshot of the state of the window manager: screens,
+    /// workspaces, windows and the focus.
+    pub fn state_snapshot(&self) -> State {
+        let screens = self
+            .screens
+            .iter()
+            .map(|screen| ScreenState {
+                output: screen.output.clone(),
+                x: screen.x,
+                y: screen.y,
+                width: screen.width,
+                height: screen.height,
+                current_workspace: screen.current_wk,
+                workspaces: screen
+                    .workspaces
+                    .iter()
+                    .map(|workspace| {
+                        let focused = workspace.focused.and_then(|w| {
+                            if workspace.ool_focus {
+                                workspace.ool_windows.get(w)
+                            } else {
+                                workspace.windows.get(w)
+                            }
+                        });
+                        let windows = workspace.windows.iter().map(|w| (w, false));
+                        let ool_windows = workspace.ool_windows.iter().map(|w| (w, true));
+                        WorkspaceState {
+                            name: workspace.name.to_string(),
+                            layout: workspace.current_layout().name().to_string(),
+                            respect_reserved_space: workspace.respect_reserved_space,
+                            focused: focused.map(|window| window.resource_id()),
+                            windows: windows
+                                .chain(ool_windows)
+                                .map(|(window, ool)| self.window_state(*window, ool))
+                                .collect(),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        State {
+            current_screen: self.current_scr,
+            focused: self.get_focused_window().map(|window| window.resource_id()),
+            screens,
+        }
+    }
+
+    fn window_state(&self, window: x::Window, ool: bool) -> WindowState {
+        let (instance, class) = self.get_class(window).unzip();
+        let cookie = self.x_connection.send_request(&x::GetGeometry {
+            drawable: x::Drawable::Window(window),
+        });
+        let geometry = self
+            .x_connection
+            .wait_for_reply(cookie)
+            .ok()
+            .map(|reply| WindowGeometry {
+                x: reply.x(),
+                y: reply.y(),
+                width: reply.width(),
+                height: reply.height(),
+                border_width: reply.border_width(),
+            });
+        WindowState {
+            id: window.resource_id(),
+            class,
+            instance,
+            title: self.get_title(window),
+            ool,
+            geometry,
+        }
+    }
+}
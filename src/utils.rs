@@ -1,12 +1,67 @@
+use std::os::fd::{AsRawFd, RawFd};
+use std::process;
+use std::time;
 use xcb;
 
-/// Gets the next X event, ignoring errors.
-pub fn get_x_event(con: &xcb::Connection) -> xcb::x::Event {
+/// Exits when the connection with the X server is lost, as there's
+/// nothing left to manage.
+fn connection_lost(error: xcb::ConnError) -> ! {
+    eprintln!("Lost the connection with the X server: {error}");
+    process::exit(1);
+}
+
+/// Gets the next X event, ignoring protocol errors, waiting at most
+/// `timeout` for it. Returns `None` if the timeout expires or if any of
+/// `fds` becomes ready for the poll events it's paired with. Waits forever
+/// if `timeout` is `None`. Exits the process if the connection with the X
+/// server is lost.
+pub fn wait_x_event(
+    con: &xcb::Connection,
+    timeout: Option<time::Duration>,
+    fds: &[(RawFd, libc::c_short)],
+) -> Option<xcb::x::Event> {
+    let deadline = timeout.map(|timeout| time::Instant::now() + timeout);
     loop {
-        if let Ok(event) = con.wait_for_event() {
-            if let xcb::Event::X(ev) = event {
-                return ev;
+        // xcb may have events already queued, so we must check before
+        // polling the socket.
+        loop {
+            match con.poll_for_event() {
+                Ok(Some(xcb::Event::X(ev))) => return Some(ev),
+                Ok(Some(_)) => {}
+                Ok(None) => break,
+                // e.g. BadWindow for a window destroyed before our request
+                // arrived. There may be more events queued after it.
+                Err(xcb::Error::Protocol(_)) => {}
+                Err(xcb::Error::Connection(error)) => connection_lost(error),
             }
         }
+        let timeout = match deadline {
+            Some(deadline) => {
+                let now = time::Instant::now();
+                if now >= deadline {
+                    return None;
+                }
+                // round up so we don't spin when less than a millisecond
+                // remains.
+                (deadline - now).as_millis() as i32 + 1
+            }
+            None => -1,
+        };
+        con.flush().ok();
+        let mut pollfds: Vec<libc::pollfd> = std::iter::once((con.as_raw_fd(), libc::POLLIN))
+            .chain(fds.iter().copied())
+            .map(|(fd, events)| libc::pollfd {
+                fd,
+                events,
+                revents: 0,
+            })
+            .collect();
+        unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, timeout) };
+        if pollfds[0].revents & (libc::POLLHUP | libc::POLLERR) != 0 {
+            connection_lost(con.has_error().err().unwrap_or(xcb::ConnError::Connection));
+        }
+        if pollfds[1..].iter().any(|fd| fd.revents != 0) {
+            return None;
+        }
     }
 }